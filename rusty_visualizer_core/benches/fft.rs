@@ -0,0 +1,62 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use num_complex::Complex32;
+
+use rusty_visualizer_core::audio::{AudioData, AudioMode};
+use rusty_visualizer_core::fft::{FFTMode, FFTSize, FftPlan, FftPlanner, process_fft};
+use rusty_visualizer_core::source::AudioFormat;
+
+fn signal(size: usize) -> Vec<f32> {
+  (0..size).map(|it| (it as f32 * 0.05).sin()).collect()
+}
+
+fn fft(c: &mut Criterion) {
+  let mut group = c.benchmark_group("fft");
+
+  for size in [FFTSize::FFT1024, FFTSize::FFT16384] {
+    let n = size as usize;
+    let input = signal(n);
+    let complex: Vec<Complex32> = input.iter().copied().map(Complex32::from).collect();
+
+    group.bench_with_input(BenchmarkId::new("process_fft", n), &complex, |b, complex| {
+      let mut data = complex.clone();
+
+      b.iter(|| {
+        data.copy_from_slice(complex);
+        process_fft(black_box(&mut data), &size, FFTMode::Backward);
+      })
+    });
+
+    group.bench_with_input(BenchmarkId::new("plan", n), &complex, |b, complex| {
+      let plan = FftPlan::new(size, FFTMode::Backward);
+      let mut data = complex.clone();
+
+      b.iter(|| {
+        data.copy_from_slice(complex);
+        plan.process(black_box(&mut data));
+      })
+    });
+
+    group.bench_with_input(BenchmarkId::new("plan_real", n), &input, |b, input| {
+      let mut plan = FftPlan::new(size, FFTMode::Backward);
+
+      b.iter(|| {
+        black_box(plan.process_real(black_box(input)));
+      })
+    });
+  }
+
+  group.finish();
+}
+
+fn analyze(c: &mut Criterion) {
+  let input = signal(FFTSize::FFT16384 as usize);
+
+  c.bench_function("audio_data_fft16384", |b| {
+    let mut planner = FftPlanner::new();
+
+    b.iter(|| AudioData::new(black_box(&input), AudioMode::FFT(FFTSize::FFT16384), AudioFormat::default(), &mut planner))
+  });
+}
+
+criterion_group!(benches, fft, analyze);
+criterion_main!(benches);
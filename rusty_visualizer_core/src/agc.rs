@@ -0,0 +1,126 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Automatic gain control, keeps the level of the samples around `target` so quiet and loud sources look alike
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AgcSettings {
+  pub enabled: bool,
+  /// RMS level to aim for
+  pub target: f32,
+  /// Seconds to react to the level going up
+  pub attack: f32,
+  /// Seconds to react to the level going down
+  pub release: f32,
+  /// Keeps silence from being turned into noise
+  pub max_gain: f32,
+}
+
+impl Default for AgcSettings {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      target: 0.25,
+      attack: 0.05,
+      release: 1.0,
+      max_gain: 50.0,
+    }
+  }
+}
+
+/// Follows the RMS level of each push and scales the samples by how far it is from the target
+pub(crate) struct Agc {
+  settings: AgcSettings,
+  level: f32,
+  gain: f32,
+}
+
+impl Agc {
+  pub(crate) fn new(settings: AgcSettings) -> Self {
+    Self {
+      settings,
+      level: settings.target,
+      gain: 1.0,
+    }
+  }
+
+  pub(crate) fn settings(&self) -> AgcSettings {
+    self.settings
+  }
+
+  pub(crate) fn set_settings(&mut self, settings: AgcSettings) {
+    self.settings = settings;
+  }
+
+  /// Applies the gain to interleaved `samples`, `frames` long at `sample_rate`
+  pub(crate) fn process(&mut self, samples: &mut [f32], frames: usize, sample_rate: u32) {
+    if samples.is_empty() || sample_rate == 0 {
+      return;
+    }
+
+    let rms = (samples.iter().map(|it| it * it).sum::<f32>() / samples.len() as f32).sqrt();
+    let time = if rms > self.level { self.settings.attack } else { self.settings.release };
+    let duration = frames as f32 / sample_rate as f32;
+    // Time constants are in seconds, so convert them for however long this push is
+    let amount = if time > 0.0 { 1.0 - (-duration / time).exp() } else { 1.0 };
+
+    self.level += (rms - self.level) * amount;
+
+    let from = self.gain;
+    let to = (self.settings.target / self.level.max(f32::EPSILON)).min(self.settings.max_gain);
+    let step = (to - from) / samples.len() as f32;
+
+    // Ramp to the new gain so it doesn't jump between pushes
+    for (index, sample) in samples.iter_mut().enumerate() {
+      *sample *= from + step * index as f32;
+    }
+
+    self.gain = to;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::agc::{Agc, AgcSettings};
+
+  fn rms(samples: &[f32]) -> f32 {
+    (samples.iter().map(|it| it * it).sum::<f32>() / samples.len() as f32).sqrt()
+  }
+
+  #[test]
+  fn levels_reach_the_target() {
+    let settings = AgcSettings {
+      enabled: true,
+      ..AgcSettings::default()
+    };
+
+    for amplitude in [0.01, 0.9] {
+      let mut agc = Agc::new(settings);
+      let mut samples = Vec::new();
+
+      // Ten seconds of a square wave at 48 kHz
+      for _ in 0..960 {
+        samples.clear();
+        samples.extend((0..500).map(|it| if it % 2 == 0 { amplitude } else { -amplitude }));
+        agc.process(&mut samples, 500, 48000);
+      }
+
+      assert!((rms(&samples) - settings.target).abs() < 0.01, "{}", rms(&samples));
+    }
+  }
+
+  #[test]
+  fn gain_is_limited() {
+    let mut agc = Agc::new(AgcSettings {
+      enabled: true,
+      max_gain: 4.0,
+      ..AgcSettings::default()
+    });
+
+    for _ in 0..1000 {
+      agc.process(&mut [0.0; 512], 512, 48000);
+    }
+
+    assert_eq!(agc.gain, 4.0);
+  }
+}
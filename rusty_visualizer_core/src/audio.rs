@@ -1,15 +1,38 @@
 use std::ops::Deref;
-use std::sync::{Arc, RwLock, RwLockReadGuard};
-
-use cpal::{Device, Host, InputCallbackInfo, Stream, SupportedStreamConfig};
-use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use num_complex::Complex32;
-use num_traits::Zero;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::sync::mpsc::{channel, Sender};
+use std::time::{Duration, Instant};
+
+use cpal::{Device, Host, SupportedStreamConfig};
+use cpal::traits::{DeviceTrait, HostTrait};
+use crossbeam_utils::atomic::AtomicCell;
 use serde::Deserialize;
 use serde::Serialize;
 
-use crate::fft::{FFTMode, FFTSize, process_fft};
+use crate::agc::AgcSettings;
+use crate::bands::BandSettings;
+use crate::broadcast::{Broadcast, Subscription};
+#[cfg(feature = "async")]
+use crate::broadcast::AudioStream;
+use crate::devices::DeviceInfo;
+use crate::error::{AudioError, AudioResult};
+use crate::event::{AudioEvent, EventSender};
+use crate::fft::{FFTMode, FFTSize, FftPlanner};
+use crate::recorder::Recording;
+use crate::scale::MagnitudeScale;
 use crate::settings::AudioSettings;
+use crate::silence::SilenceSettings;
+use crate::sink::{MAX_FFT_SIZE, SinkCommand};
+use crate::stft::StftSettings;
+use crate::stream::StreamSettings;
+use crate::source::{
+  AudioFormat, AudioSink, AudioSource, DeviceSource, FileSettings, FileSource, GeneratorSettings, GeneratorSource, PipeSettings,
+  PipeSource,
+};
+use crate::triple_buffer::{triple_buffer, TripleBufferOutput};
+use crate::watchdog::{Heartbeat, SourceStatus, Watchdog};
+use crate::window::WindowFunction;
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
 pub enum AudioMode {
@@ -51,45 +74,195 @@ impl AudioMode {
   }
 }
 
-#[derive(Clone)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum PlaybackState {
+  #[default]
+  Playing,
+  /// Keeps the last frame around
+  Paused,
+  /// Like paused, but without any data
+  Stopped,
+}
+
+impl PlaybackState {
+  pub const fn name(&self) -> &'static str {
+    match self {
+      PlaybackState::Playing => "Playing",
+      PlaybackState::Paused => "Paused",
+      PlaybackState::Stopped => "Stopped",
+    }
+  }
+}
+
+/// How the channels of a multichannel source end up in [`AudioData`]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum ChannelMode {
+  /// Average of every channel
+  #[default]
+  Mono,
+  Left,
+  Right,
+  /// Average of left and right, what they have in common
+  Mid,
+  /// Half the difference between left and right, what's only in one of them
+  Side,
+  /// Mono downmix plus every channel on its own in [`AudioData::channels`]
+  Split,
+}
+
+impl ChannelMode {
+  pub const ALL: &'static [Self] = &[
+    ChannelMode::Mono,
+    ChannelMode::Left,
+    ChannelMode::Right,
+    ChannelMode::Mid,
+    ChannelMode::Side,
+    ChannelMode::Split,
+  ];
+
+  pub const fn name(&self) -> &'static str {
+    match self {
+      ChannelMode::Mono => "Mono",
+      ChannelMode::Left => "Left",
+      ChannelMode::Right => "Right",
+      ChannelMode::Mid => "Mid",
+      ChannelMode::Side => "Side",
+      ChannelMode::Split => "Split",
+    }
+  }
+
+  /// Turns one interleaved frame into a single sample, mono frames are treated as both left and right
+  /// and empty ones as silence
+  pub fn mix(&self, frame: &[f32]) -> f32 {
+    if frame.is_empty() {
+      return 0.0;
+    }
+
+    let left = frame[0];
+    let right = frame[1.min(frame.len() - 1)];
+
+    match self {
+      ChannelMode::Mono | ChannelMode::Split => frame.iter().sum::<f32>() / frame.len() as f32,
+      ChannelMode::Left => left,
+      ChannelMode::Right => right,
+      ChannelMode::Mid => (left + right) / 2.0,
+      ChannelMode::Side => (left - right) / 2.0,
+    }
+  }
+}
+
 pub struct AudioData {
   pub data: Vec<f32>,
   pub sum: f32,
   pub mode: AudioMode,
+  /// Analysis of every channel on its own, empty unless [`ChannelMode::Split`] is used
+  pub channels: Vec<AudioData>,
+  pub sample_rate: u32,
+  /// How many channels the source has, not how many are in [`channels`](Self::channels)
+  pub channel_count: u16,
+  /// [`data`](Self::data) grouped into log spaced bands, empty unless [`BandSettings`] are enabled in [`AudioMode::FFT`]
+  pub bands: Vec<f32>,
+  /// Counts up by one every frame analyzed since the source started, so a gap means frames were missed
+  pub frame: u64,
+  /// When the samples reached the sink
+  pub callback: Instant,
+  /// When the newest of the samples was captured, based on the latency the source reports
+  pub capture: Instant,
 }
 
 impl AudioData {
-  fn new(data: &[f32], mode: AudioMode) -> Self {
-    let mut sum = 0.0f32;
+  /// Analyzes `data` on its own, `planner` keeps the FFT plans around for the next call
+  pub fn new(data: &[f32], mode: AudioMode, format: AudioFormat, planner: &mut FftPlanner) -> Self {
+    let mut result = AudioData::with_capacity(0);
+    result.analyze(data, mode, format, MagnitudeScale::default(), planner);
+    result
+  }
 
-    let process = |it: f32| {
-      sum += it;
-      it
-    };
+  pub(crate) fn with_capacity(capacity: usize) -> Self {
+    let format = AudioFormat::default();
+    let now = Instant::now();
+
+    AudioData {
+      data: Vec::with_capacity(capacity),
+      sum: 0.0,
+      mode: AudioMode::Wave,
+      channels: Vec::new(),
+      sample_rate: format.sample_rate,
+      channel_count: format.channels,
+      bands: Vec::new(),
+      frame: 0,
+      callback: now,
+      capture: now,
+    }
+  }
 
-    let data = match mode {
-      AudioMode::Wave => data.iter().map(|it| *it).map(process).collect(),
+  /// Same as [`new`](Self::new) but reuses the allocations of `self` and `planner`,
+  /// doesn't allocate once they're big enough
+  pub(crate) fn analyze(
+    &mut self,
+    data: &[f32],
+    mode: AudioMode,
+    format: AudioFormat,
+    scale: MagnitudeScale,
+    planner: &mut FftPlanner,
+  ) {
+    self.data.clear();
+
+    match mode {
+      AudioMode::Wave => self.data.extend_from_slice(data),
       AudioMode::FFT(size) => {
-        let size_v = size as usize;
-        let len = data.len() + size_v + 1;
-        let mut buffer = vec![Complex32::zero(); len];
-
-        for i in 0..data.len() {
-          buffer[i] = Complex32::from(data[i]);
-        }
-
-        process_fft(&mut buffer, &size, FFTMode::Backward);
-
-        buffer
-          .iter()
-          .map(|it| (it.re * it.re + it.im * it.im).sqrt().sqrt() / 10f32)
-          .take(size_v)
-          .map(process)
-          .collect()
+        let n = size as usize;
+        let spectrum = planner.plan(size, FFTMode::Backward).process_real(data);
+
+        // Bins past the middle mirror the ones before it
+        self.data.extend((0..n).map(|it| it.min(n - it)).map(|it| scale.apply(spectrum[it], it, n)));
       }
+    }
+
+    self.sum = self.data.iter().sum();
+    self.mode = mode;
+    self.sample_rate = format.sample_rate;
+    self.channel_count = format.channels;
+  }
+
+  /// Hz between two bins, `None` in [`AudioMode::Wave`]
+  pub fn bin_width(&self) -> Option<f32> {
+    match self.mode {
+      AudioMode::FFT(size) => Some(self.sample_rate as f32 / size as usize as f32),
+      AudioMode::Wave => None,
+    }
+  }
+
+  /// Hz at the center of `bin`, bins past the middle mirror the ones before it so they're folded back,
+  /// `None` past the last bin
+  pub fn bin_frequency(&self, bin: usize) -> Option<f32> {
+    let width = self.bin_width()?;
+    let size = match self.mode {
+      AudioMode::FFT(size) => size as usize,
+      AudioMode::Wave => return None,
     };
 
-    AudioData { data, sum, mode }
+    if bin >= size {
+      return None;
+    }
+
+    Some(bin.min(size - bin) as f32 * width)
+  }
+
+  /// Closest bin to `hz`, `None` when it's below 0 or above the nyquist frequency
+  pub fn frequency_to_bin(&self, hz: f32) -> Option<usize> {
+    let nyquist = self.sample_rate as f32 / 2.0;
+
+    if !(0.0..=nyquist).contains(&hz) {
+      return None;
+    }
+
+    Some((hz / self.bin_width()?).round() as usize)
+  }
+
+  /// Time since the newest sample was captured, which is the latency when called right before drawing it
+  pub fn age(&self) -> Duration {
+    self.capture.elapsed()
   }
 }
 
@@ -101,9 +274,42 @@ impl Deref for AudioData {
   }
 }
 
+impl Clone for AudioData {
+  fn clone(&self) -> Self {
+    let mut clone = AudioData::with_capacity(0);
+    clone.clone_from(self);
+    clone
+  }
+
+  /// Reuses the allocations of `self`, including the ones of its channels
+  fn clone_from(&mut self, source: &Self) {
+    self.data.clone_from(&source.data);
+    self.sum = source.sum;
+    self.mode = source.mode;
+    self.channels.clone_from(&source.channels);
+    self.sample_rate = source.sample_rate;
+    self.channel_count = source.channel_count;
+    self.bands.clone_from(&source.bands);
+    self.frame = source.frame;
+    self.callback = source.callback;
+    self.capture = source.capture;
+  }
+}
+
 impl Default for AudioData {
   fn default() -> Self {
-    AudioData::new(&[], AudioMode::Wave)
+    AudioData::with_capacity(0)
+  }
+}
+
+/// Latest [`AudioData`] from [`Audio::data`]
+pub struct AudioDataGuard<'a>(MutexGuard<'a, TripleBufferOutput<AudioData>>);
+
+impl Deref for AudioDataGuard<'_> {
+  type Target = AudioData;
+
+  fn deref(&self) -> &Self::Target {
+    self.0.peek()
   }
 }
 
@@ -111,27 +317,63 @@ unsafe impl Send for Audio {}
 
 pub struct Audio {
   host: Host,
-  mode: Arc<RwLock<AudioMode>>,
-  stream: Option<Stream>,
-  receiver: Option<Arc<RwLock<AudioData>>>,
-  auto_play: bool,
+  mode: Arc<AtomicCell<AudioMode>>,
+  channel_mode: Arc<AtomicCell<ChannelMode>>,
+  source: Option<Box<dyn AudioSource>>,
+  receiver: Option<Mutex<TripleBufferOutput<AudioData>>>,
+  state: PlaybackState,
+  watchdog: Watchdog,
+  broadcast: Broadcast,
+  commands: Option<Sender<SinkCommand>>,
+  recording: Option<Recording>,
+  stream: StreamSettings,
+  agc: AgcSettings,
+  silence: SilenceSettings,
+  window: WindowFunction,
+  stft: StftSettings,
+  scale: MagnitudeScale,
+  bands: BandSettings,
+  events: EventSender,
+  /// Beats whenever the current source pushes samples
+  heartbeat: Option<Arc<Heartbeat>>,
+  /// Last silence state given out by [`events`](Self::events)
+  silent: bool,
 }
 
 impl From<&AudioSettings> for Audio {
   fn from(settings: &AudioSettings) -> Self {
     let host = cpal::default_host();
-    let mode = Arc::new(RwLock::new(settings.mode));
+    let mode = Arc::new(AtomicCell::new(settings.mode));
+    let channel_mode = Arc::new(AtomicCell::new(settings.channel_mode));
+
 
     let mut audio = Audio {
       host,
       mode,
-      stream: None,
+      channel_mode,
+      source: None,
       receiver: None,
-      auto_play: settings.auto_play,
+      state: settings.state,
+      watchdog: Watchdog::new(),
+      broadcast: Broadcast::new(),
+      commands: None,
+      recording: None,
+      stream: settings.stream,
+      agc: settings.agc,
+      silence: settings.silence,
+      window: settings.window,
+      stft: settings.stft,
+      scale: settings.scale,
+      bands: settings.bands,
+      events: EventSender::default(),
+      heartbeat: None,
+      silent: false,
     };
 
     if settings.auto_set {
-      audio.change_device(settings.device.clone());
+      if let Err(err) = audio.open_device(settings) {
+        eprintln!("{}", err);
+      }
     }
 
     return audio;
@@ -145,12 +387,27 @@ pub enum AudioDevice<D: NamedAudioDevice = ()> {
   Loopback,
   Input(D),
   Output(D),
+  File(FileSettings),
+  Generator(GeneratorSettings),
+  Pipe(PipeSettings),
 }
 
 impl AudioDevice {
   pub const NONE: Self = Self::None;
   pub const DEFAULT: Self = Self::Default;
   pub const LOOPBACK: Self = Self::Loopback;
+
+  pub fn file(settings: FileSettings) -> Self {
+    Self::File(settings)
+  }
+
+  pub fn generator(settings: GeneratorSettings) -> Self {
+    Self::Generator(settings)
+  }
+
+  pub fn pipe(settings: PipeSettings) -> Self {
+    Self::Pipe(settings)
+  }
 }
 
 impl<D: NamedAudioDevice> Default for AudioDevice<D> {
@@ -160,11 +417,16 @@ impl<D: NamedAudioDevice> Default for AudioDevice<D> {
 }
 
 pub trait NamedAudioDevice: Send {
-  fn to_device(self, host: &Host) -> Option<Device>;
+  fn to_device(self, host: &Host) -> AudioResult<Device>;
 }
 
 pub trait NamedAudioDeviceWithConfig: Send {
-  fn to_device(self, host: &Host) -> Option<(SupportedStreamConfig, Device)>;
+  fn to_device(self, host: &Host, stream: &StreamSettings) -> AudioResult<(SupportedStreamConfig, Device)>;
+}
+
+pub trait ToAudioSource {
+  /// `None` when there shouldn't be a source at all
+  fn to_source(self, host: &Host, stream: &StreamSettings) -> AudioResult<Option<Box<dyn AudioSource>>>;
 }
 
 pub trait ToSerializableAudioDevice {
@@ -178,58 +440,83 @@ impl<D: NamedAudioDevice> ToSerializableAudioDevice for AudioDevice<D> {
       AudioDevice::Default => AudioDevice::Default,
       AudioDevice::Loopback => AudioDevice::Loopback,
       AudioDevice::Input(device) => match device.to_device(audio.host()) {
-        None => AudioDevice::None,
-        Some(device) => AudioDevice::Input(device.name().unwrap_or_default()),
+        Err(_) => AudioDevice::None,
+        Ok(device) => AudioDevice::Input(device.name().unwrap_or_default()),
       },
       AudioDevice::Output(device) => match device.to_device(audio.host()) {
-        None => AudioDevice::None,
-        Some(device) => AudioDevice::Output(device.name().unwrap_or_default()),
+        Err(_) => AudioDevice::None,
+        Ok(device) => AudioDevice::Output(device.name().unwrap_or_default()),
       },
+      AudioDevice::File(settings) => AudioDevice::File(settings),
+      AudioDevice::Generator(settings) => AudioDevice::Generator(settings),
+      AudioDevice::Pipe(settings) => AudioDevice::Pipe(settings),
     }
   }
 }
 
 impl<D: NamedAudioDevice> NamedAudioDeviceWithConfig for AudioDevice<D> {
-  fn to_device(self, host: &Host) -> Option<(SupportedStreamConfig, Device)> {
-    match self {
-      AudioDevice::Default => "default".to_device(host).map(|it| (it.default_input_config().unwrap(), it)),
-      AudioDevice::Loopback => "loopback".to_device(host).map(|it| (it.default_output_config().unwrap(), it)),
-      AudioDevice::Input(device) => device.to_device(host).map(|it| (it.default_input_config().unwrap(), it)),
-      AudioDevice::Output(device) => device.to_device(host).map(|it| (it.default_output_config().unwrap(), it)),
-      _ => None,
-    }
+  fn to_device(self, host: &Host, stream: &StreamSettings) -> AudioResult<(SupportedStreamConfig, Device)> {
+    let (device, input) = match self {
+      AudioDevice::Default => ("default".to_device(host)?, true),
+      AudioDevice::Loopback => ("loopback".to_device(host)?, false),
+      AudioDevice::Input(device) => (device.to_device(host)?, true),
+      AudioDevice::Output(device) => (device.to_device(host)?, false),
+      _ => return Err(AudioError::DeviceNotFound(String::from("none"))),
+    };
+
+    Ok((stream.negotiate(&device, input)?, device))
+  }
+}
+
+impl<D: NamedAudioDevice> ToAudioSource for AudioDevice<D> {
+  fn to_source(self, host: &Host, stream: &StreamSettings) -> AudioResult<Option<Box<dyn AudioSource>>> {
+    let source: Box<dyn AudioSource> = match self {
+      AudioDevice::None => return Ok(None),
+      AudioDevice::File(settings) => Box::new(FileSource::new(settings)),
+      AudioDevice::Generator(settings) => Box::new(GeneratorSource::new(settings)),
+      AudioDevice::Pipe(settings) => Box::new(PipeSource::new(settings)),
+      device => {
+        let (config, device) = NamedAudioDeviceWithConfig::to_device(device, host, stream)?;
+        let buffer_size = stream.buffer_size(config.buffer_size());
+
+        Box::new(DeviceSource::new(config, device).with_buffer_size(buffer_size))
+      }
+    };
+
+    Ok(Some(source))
   }
 }
 
 impl NamedAudioDevice for () {
-  fn to_device(self, _host: &Host) -> Option<Device> {
-    None
+  fn to_device(self, _host: &Host) -> AudioResult<Device> {
+    Err(AudioError::DeviceNotFound(String::from("none")))
   }
 }
 
 impl NamedAudioDevice for &str {
-  fn to_device(self, host: &Host) -> Option<Device> {
-    match self {
+  fn to_device(self, host: &Host) -> AudioResult<Device> {
+    let device = match self {
       "default" => host.default_input_device(),
       "loopback" => host.default_output_device(),
       "none" | "" => None,
       _ => host
-        .devices()
-        .unwrap()
+        .devices()?
         .find(|it| it.name().unwrap_or_else(|_| String::from("")) == self),
-    }
+    };
+
+    device.ok_or_else(|| AudioError::DeviceNotFound(self.to_string()))
   }
 }
 
 impl NamedAudioDevice for String {
-  fn to_device(self, host: &Host) -> Option<Device> {
+  fn to_device(self, host: &Host) -> AudioResult<Device> {
     NamedAudioDevice::to_device(self.as_ref(), host)
   }
 }
 
 impl NamedAudioDevice for Device {
-  fn to_device(self, _host: &Host) -> Option<Device> {
-    Some(self)
+  fn to_device(self, _host: &Host) -> AudioResult<Device> {
+    Ok(self)
   }
 }
 
@@ -242,55 +529,450 @@ impl Audio {
     &self.host
   }
 
-  pub fn stream(&self) -> &Option<Stream> {
-    &self.stream
+  /// Every device that can be captured from, outputs are captured through loopback
+  pub fn devices(&self) -> AudioResult<Vec<DeviceInfo>> {
+    crate::devices::devices(&self.host)
   }
 
-  pub fn data(&self) -> Option<RwLockReadGuard<AudioData>> {
-    self.receiver.as_ref()?.read().ok()
+  pub fn source(&self) -> Option<&dyn AudioSource> {
+    self.source.as_deref()
+  }
+
+  /// `None` when there isn't a source or it's stopped
+  pub fn data(&self) -> Option<AudioDataGuard<'_>> {
+    if self.state == PlaybackState::Stopped {
+      return None;
+    }
+
+    let mut receiver = self.receiver.as_ref()?.lock().ok()?;
+    receiver.update();
+
+    Some(AudioDataGuard(receiver))
+  }
+
+  pub fn state(&self) -> PlaybackState {
+    self.state
+  }
+
+  pub fn is_playing(&self) -> bool {
+    self.state == PlaybackState::Playing
+  }
+
+  pub fn play(&mut self) -> AudioResult<()> {
+    self.change_state(PlaybackState::Playing)
+  }
+
+  /// Stops capturing, [`data`](Self::data) keeps the last frame
+  pub fn pause(&mut self) -> AudioResult<()> {
+    self.change_state(PlaybackState::Paused)
+  }
+
+  /// Stops capturing without dropping the source, there won't be any data until it plays again
+  pub fn stop(&mut self) -> AudioResult<()> {
+    self.change_state(PlaybackState::Stopped)
+  }
+
+  pub fn change_state(&mut self, state: PlaybackState) -> AudioResult<()> {
+    if let Some(source) = &mut self.source {
+      match state {
+        PlaybackState::Playing => source.play()?,
+        PlaybackState::Paused | PlaybackState::Stopped => source.pause()?,
+      }
+    }
+
+    self.state = state;
+
+    Ok(())
+  }
+
+  /// Receives every frame from now on, unlike [`data`](Self::data) which only has the latest one,
+  /// as long as they're received before [`SUBSCRIPTION_CAPACITY`](crate::broadcast::SUBSCRIPTION_CAPACITY) pile up
+  pub fn subscribe(&mut self) -> Subscription {
+    let (subscription, subscriber) = self.broadcast.subscribe();
+    self.send_command(SinkCommand::Subscribe(subscriber));
+
+    subscription
+  }
+
+  /// Same as [`subscribe`](Self::subscribe), but frames can be awaited from an async runtime
+  #[cfg(feature = "async")]
+  pub fn stream(&mut self) -> AudioStream {
+    let (stream, subscriber) = self.broadcast.stream();
+    self.send_command(SinkCommand::Subscribe(subscriber));
+
+    stream
+  }
+
+  /// Writes the samples of the current source to a wav file as they arrive, until
+  /// [`stop_recording`](Self::stop_recording) or the source changes
+  pub fn start_recording(&mut self, path: impl AsRef<Path>) -> AudioResult<()> {
+    if self.commands.is_none() {
+      return Err(AudioError::NoSource);
+    }
+
+    self.stop_recording()?;
+
+    let (recording, tap) = Recording::create(path)?;
+    self.send_command(SinkCommand::Record(tap));
+    self.recording = Some(recording);
+
+    Ok(())
+  }
+
+  pub fn stop_recording(&mut self) -> AudioResult<()> {
+    match self.recording.take() {
+      Some(recording) => recording.stop(),
+      None => Ok(()),
+    }
+  }
+
+  pub fn recording(&self) -> Option<&Recording> {
+    self.recording.as_ref()
+  }
+
+  pub fn is_recording(&self) -> bool {
+    self.recording.is_some()
+  }
+
+  fn send_command(&self, command: SinkCommand) {
+    if let Some(commands) = &self.commands {
+      // Fails when the source is already gone, the command wouldn't do anything anyway
+      let _ = commands.send(command);
+    }
   }
 
   pub fn mode(&self) -> AudioMode {
-    *self.mode.read().unwrap()
+    self.mode.load()
   }
 
   pub fn change_mode(&mut self, new_mode: AudioMode) {
-    *self.mode.write().unwrap() = new_mode;
-  }
-
-  pub fn change_device(&mut self, new_device: impl NamedAudioDeviceWithConfig) {
-    crossbeam_utils::thread::scope(|s| {
-      s.spawn(|_| match new_device.to_device(&self.host) {
-        None => {
-          self.stream = None;
-          self.receiver = None;
-        }
-        Some((config, device)) => {
-          let sender = Arc::new(RwLock::new(AudioData::default()));
-          let receiver = sender.clone();
-          let mode = self.mode.clone();
-
-          let stream = device
-            .build_input_stream(
-              &config.config(),
-              move |data: &[f32], _: &InputCallbackInfo| {
-                *sender.write().unwrap() = AudioData::new(data, *mode.read().unwrap());
-              },
-              move |err| println!("{:?}", err),
-            )
-            .unwrap();
-
-          if self.auto_play {
-            stream.play().unwrap();
-          }
-
-          self.stream = Some(stream);
-          self.receiver = Some(receiver);
-
-          println!("Changed Device: {}", device.name().unwrap_or_default());
-        }
-      });
-    })
-      .unwrap();
+    self.mode.store(new_mode);
+  }
+
+  pub fn channel_mode(&self) -> ChannelMode {
+    self.channel_mode.load()
+  }
+
+  pub fn change_channel_mode(&mut self, new_mode: ChannelMode) {
+    self.channel_mode.store(new_mode);
+  }
+
+  pub fn agc(&self) -> AgcSettings {
+    self.agc
+  }
+
+  pub fn change_agc(&mut self, agc: AgcSettings) {
+    self.agc = agc;
+    self.send_command(SinkCommand::Agc(agc));
+  }
+
+  pub fn silence(&self) -> SilenceSettings {
+    self.silence
+  }
+
+  pub fn change_silence(&mut self, silence: SilenceSettings) {
+    self.silence = silence;
+    self.send_command(SinkCommand::Silence(silence));
+  }
+
+  pub fn window(&self) -> WindowFunction {
+    self.window
+  }
+
+  pub fn change_window(&mut self, window: WindowFunction) {
+    self.window = window;
+    self.send_command(SinkCommand::Window(window));
+  }
+
+  pub fn stft(&self) -> StftSettings {
+    self.stft
+  }
+
+  pub fn change_stft(&mut self, stft: StftSettings) {
+    self.stft = stft;
+    self.send_command(SinkCommand::Stft(stft));
+  }
+
+  pub fn scale(&self) -> MagnitudeScale {
+    self.scale
+  }
+
+  pub fn change_scale(&mut self, scale: MagnitudeScale) {
+    self.scale = scale;
+    self.send_command(SinkCommand::Scale(scale));
+  }
+
+  pub fn bands(&self) -> BandSettings {
+    self.bands
+  }
+
+  pub fn change_bands(&mut self, bands: BandSettings) {
+    self.bands = bands;
+    self.send_command(SinkCommand::Bands(bands));
+  }
+
+  /// Whether there's nothing to show, because there isn't a source, it isn't playing,
+  /// it stopped sending samples or what it sends has been quiet for the silence hold time
+  pub fn is_silent(&self) -> bool {
+    let stalled = match &self.heartbeat {
+      Some(heartbeat) => heartbeat.silence().as_secs_f32() >= self.silence.hold,
+      None => true,
+    };
+
+    !self.is_playing() || stalled || self.events.is_silent()
+  }
+
+  /// Events that happened since the last time this was called, without waiting for more,
+  /// meant to be called every frame since a source that stops sending samples can't send events either
+  pub fn events(&mut self) -> impl Iterator<Item = AudioEvent> {
+    let silent = self.is_silent();
+    let event = match (self.silent, silent) {
+      (false, true) => Some(AudioEvent::SilenceStarted),
+      (true, false) => Some(AudioEvent::SilenceEnded),
+      _ => None,
+    };
+
+    self.silent = silent;
+    event.into_iter()
+  }
+
+  pub fn stream_settings(&self) -> StreamSettings {
+    self.stream
+  }
+
+  /// Used the next time a device is opened
+  pub fn change_stream_settings(&mut self, stream: StreamSettings) {
+    self.stream = stream;
+  }
+
+  /// Switches to `new_device`, there won't be any source if it fails
+  pub fn change_device(&mut self, new_device: impl ToAudioSource) -> AudioResult<()> {
+    // Drop the previous source first so devices are released before opening new ones
+    self.remove_source();
+
+    match new_device.to_source(&self.host, &self.stream)? {
+      None => Ok(()),
+      Some(source) => self.change_source(source),
+    }
+  }
+
+  /// Switches to `settings.device` like [`change_device`](Self::change_device), but if it fails
+  /// [`watch`](Self::watch) keeps trying to open it, like when it isn't plugged in yet
+  pub fn open_device(&mut self, settings: &AudioSettings) -> AudioResult<()> {
+    let result = self.change_device(settings.device.clone());
+
+    if let Err(err) = &result {
+      if settings.watchdog.enabled {
+        self.watchdog.fail(err.to_string(), &settings.watchdog);
+      }
+    }
+
+    result
+  }
+
+  /// Switches to `source`, there won't be any source if it fails to start
+  pub fn change_source(&mut self, source: impl AudioSource + 'static) -> AudioResult<()> {
+    self.remove_source();
+
+    let mut source = Box::new(source);
+    let (sender, receiver) = triple_buffer(|| AudioData::with_capacity(MAX_FFT_SIZE));
+    let (commands, command_receiver) = channel();
+    // Sources that aren't live can wait for subscribers to catch up instead of dropping frames
+    let sink = AudioSink::new(
+      self.mode.clone(),
+      self.channel_mode.clone(),
+      sender,
+      command_receiver,
+      self.events.clone(),
+      !source.is_live(),
+    );
+    let heartbeat = sink.heartbeat();
+
+    let _ = commands.send(SinkCommand::Agc(self.agc));
+    let _ = commands.send(SinkCommand::Silence(self.silence));
+    let _ = commands.send(SinkCommand::Window(self.window));
+    let _ = commands.send(SinkCommand::Stft(self.stft));
+    let _ = commands.send(SinkCommand::Scale(self.scale));
+    let _ = commands.send(SinkCommand::Bands(self.bands));
+
+    for subscriber in self.broadcast.subscribers() {
+      let _ = commands.send(SinkCommand::Subscribe(subscriber.clone()));
+    }
+
+    source.start(sink)?;
+
+    if self.is_playing() {
+      source.play()?;
+    }
+
+    println!("Changed Source: {}", source.name());
+
+    self.watchdog.start(heartbeat.clone(), source.is_live());
+    self.heartbeat = Some(heartbeat);
+    self.source = Some(source);
+    self.receiver = Some(Mutex::new(receiver));
+    self.commands = Some(commands);
+
+    Ok(())
+  }
+
+  pub fn remove_source(&mut self) {
+    // A sink waiting for subscribers would keep the source from stopping
+    if let Some(heartbeat) = self.heartbeat.take() {
+      heartbeat.close();
+    }
+
+    self.source = None;
+    self.receiver = None;
+    self.commands = None;
+    self.recording = None;
+    self.watchdog.stop();
+  }
+
+  pub fn status(&self) -> &SourceStatus {
+    self.watchdog.status()
+  }
+
+  /// Reopens `settings.device` when the source errored or stopped sending audio,
+  /// with a growing delay between attempts, meant to be called every frame.
+  pub fn watch(&mut self, settings: &AudioSettings) -> &SourceStatus {
+    if let Some(attempt) = self.watchdog.check(&settings.watchdog, self.is_playing()) {
+      let result = self.change_device(settings.device.clone());
+
+      self.watchdog.retried(attempt);
+
+      if let Err(err) = result {
+        self.watchdog.fail(err.to_string(), &settings.watchdog);
+      }
+    }
+
+    self.watchdog.status()
+  }
+}
+
+impl Drop for Audio {
+  /// Same as removing the source, so it's closed before being dropped
+  fn drop(&mut self) {
+    self.remove_source();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::{Arc, Mutex};
+  use std::time::Duration;
+
+  use crate::audio::{Audio, AudioDevice, AudioMode, ChannelMode, PlaybackState};
+  use crate::event::AudioEvent;
+  use crate::settings::AudioSettings;
+  use crate::silence::SilenceSettings;
+  use crate::source::PipeSettings;
+  use crate::source::tests::{SharedSource, SliceSource, audio};
+  use crate::watchdog::SourceStatus;
+
+  #[test]
+  fn empty_frames_mix_to_silence() {
+    for mode in ChannelMode::ALL {
+      assert_eq!(mode.mix(&[]), 0.0);
+    }
+  }
+
+  #[test]
+  fn stopping_hides_data_until_playing() {
+    let mut audio = audio(AudioMode::Wave);
+    audio.change_source(SliceSource(vec![1.0])).unwrap();
+
+    audio.pause().unwrap();
+    assert!(!audio.is_playing());
+    assert_eq!(audio.data().unwrap().data, vec![1.0]);
+
+    audio.stop().unwrap();
+    assert!(audio.data().is_none());
+
+    audio.play().unwrap();
+    assert!(audio.is_playing());
+    assert_eq!(audio.data().unwrap().data, vec![1.0]);
+  }
+
+  fn events(audio: &mut Audio) -> Vec<AudioEvent> {
+    audio.events().collect()
+  }
+
+  #[test]
+  fn stopped_state_is_restored() {
+    let settings = AudioSettings {
+      state: PlaybackState::Stopped,
+      auto_set: false,
+      ..AudioSettings::default()
+    };
+    let json = serde_json::to_string(&settings).unwrap();
+    let audio = Audio::from(&serde_json::from_str::<AudioSettings>(&json).unwrap());
+
+    assert_eq!(audio.state(), PlaybackState::Stopped);
+  }
+
+  #[test]
+  fn silent_without_samples() {
+    let mut audio = Audio::from(&AudioSettings {
+      mode: AudioMode::Wave,
+      auto_set: false,
+      silence: SilenceSettings {
+        hold: 0.05,
+        ..SilenceSettings::default()
+      },
+      ..AudioSettings::default()
+    });
+
+    assert!(audio.is_silent());
+    assert_eq!(events(&mut audio), vec![AudioEvent::SilenceStarted]);
+
+    let sink = Arc::new(Mutex::new(None));
+    audio.change_source(SharedSource(sink.clone())).unwrap();
+    let push = || sink.lock().unwrap().as_mut().unwrap().push(&[1.0]);
+
+    push();
+    assert!(!audio.is_silent());
+    assert_eq!(events(&mut audio), vec![AudioEvent::SilenceEnded]);
+
+    // Not playing
+    audio.pause().unwrap();
+    assert_eq!(events(&mut audio), vec![AudioEvent::SilenceStarted]);
+    audio.play().unwrap();
+    assert_eq!(events(&mut audio), vec![AudioEvent::SilenceEnded]);
+
+    // The source stopped sending anything
+    std::thread::sleep(Duration::from_millis(100));
+    assert_eq!(events(&mut audio), vec![AudioEvent::SilenceStarted]);
+    push();
+    assert_eq!(events(&mut audio), vec![AudioEvent::SilenceEnded]);
+
+    audio.remove_source();
+    assert_eq!(events(&mut audio), vec![AudioEvent::SilenceStarted]);
+    assert!(events(&mut audio).is_empty());
+  }
+
+  #[test]
+  fn missing_devices_are_retried() {
+    let path = std::env::temp_dir().join("rusty_visualizer_missing_devices_are_retried");
+    let _ = std::fs::remove_file(&path);
+
+    let mut settings = AudioSettings {
+      mode: AudioMode::Wave,
+      device: AudioDevice::Pipe(PipeSettings {
+        path: Some(path.clone()),
+        ..PipeSettings::default()
+      }),
+      ..AudioSettings::default()
+    };
+    settings.watchdog.min_backoff = 0.0;
+
+    let mut audio = Audio::from(&settings);
+    assert!(matches!(audio.status(), SourceStatus::Recovering { .. }));
+
+    std::fs::write(&path, []).unwrap();
+    assert_eq!(*audio.watch(&settings), SourceStatus::Running);
+
+    drop(audio);
+    std::fs::remove_file(path).unwrap();
   }
 }
@@ -0,0 +1,157 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+/// How the bins in a band are combined
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum BandAggregation {
+  /// Loudest bin, keeps peaks sharp
+  #[default]
+  Max,
+  /// Root mean square of the bins, smoother
+  Rms,
+}
+
+impl BandAggregation {
+  pub const ALL: &'static [Self] = &[BandAggregation::Max, BandAggregation::Rms];
+
+  pub const fn name(&self) -> &'static str {
+    match self {
+      BandAggregation::Max => "Max",
+      BandAggregation::Rms => "RMS",
+    }
+  }
+}
+
+/// Groups FFT bins into bands that are evenly spaced in octaves, the way pitch is heard,
+/// instead of in Hz where the bass only gets a few bins
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BandSettings {
+  pub enabled: bool,
+  /// 1 for octaves, 3 for third octaves and so on
+  pub bands_per_octave: u32,
+  pub min_hz: f32,
+  /// Clamped to the nyquist frequency
+  pub max_hz: f32,
+  pub aggregation: BandAggregation,
+}
+
+impl Default for BandSettings {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      bands_per_octave: 3,
+      min_hz: 20.0,
+      max_hz: 20000.0,
+      aggregation: BandAggregation::Max,
+    }
+  }
+}
+
+impl BandSettings {
+  /// Lowest and highest frequency of every band, up to `nyquist`
+  pub fn edges(&self, nyquist: f32) -> impl Iterator<Item = (f32, f32)> {
+    let min = self.min_hz.max(1.0);
+    let max = self.max_hz.min(nyquist);
+    let per_octave = self.bands_per_octave.max(1) as f32;
+    let step = 2f32.powf(1.0 / per_octave);
+    // A little less so a range of exactly N bands doesn't get an empty one from rounding
+    let count = if max > min { ((max / min).log2() * per_octave - 1e-4).ceil() as usize } else { 0 };
+
+    (0..count).map(move |it| {
+      let low = min * step.powi(it as i32);
+      (low, (low * step).min(max))
+    })
+  }
+
+  /// Aggregates FFT magnitudes `data`, `bin_width` Hz apart, into `bands`
+  pub(crate) fn aggregate(&self, data: &[f32], bin_width: f32, bands: &mut Vec<f32>) {
+    bands.clear();
+
+    // Only the first half, the rest mirrors it
+    let half = &data[..(data.len() / 2 + 1).min(data.len())];
+
+    if half.is_empty() || bin_width <= 0.0 {
+      return;
+    }
+
+    let nyquist = (half.len() - 1) as f32 * bin_width;
+
+    bands.extend(self.edges(nyquist).map(|(low, high)| {
+      let first = (low / bin_width).ceil() as usize;
+      let last = ((high / bin_width).ceil() as usize).min(half.len());
+
+      // Bands narrower than a bin still get the closest one, or the bass would be full of gaps
+      let bins = if first < last {
+        &half[first..last]
+      } else {
+        let center = ((low * high).sqrt() / bin_width).round() as usize;
+        let center = center.min(half.len() - 1);
+        &half[center..=center]
+      };
+
+      match self.aggregation {
+        BandAggregation::Max => bins.iter().copied().fold(0.0, f32::max),
+        BandAggregation::Rms => (bins.iter().map(|it| it * it).sum::<f32>() / bins.len() as f32).sqrt(),
+      }
+    }));
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::bands::{BandAggregation, BandSettings};
+
+  #[test]
+  fn bands_are_spaced_in_octaves() {
+    let settings = BandSettings {
+      enabled: true,
+      bands_per_octave: 1,
+      min_hz: 100.0,
+      max_hz: 1000.0,
+      ..BandSettings::default()
+    };
+
+    let edges: Vec<(f32, f32)> = settings.edges(24000.0).collect();
+    assert_eq!(edges, vec![(100.0, 200.0), (200.0, 400.0), (400.0, 800.0), (800.0, 1000.0)]);
+    assert_eq!(settings.edges(300.0).count(), 2);
+  }
+
+  #[test]
+  fn aggregates_bins() {
+    let mut settings = BandSettings {
+      enabled: true,
+      bands_per_octave: 1,
+      min_hz: 1.0,
+      max_hz: 4.0,
+      ..BandSettings::default()
+    };
+
+    // Bins at 0 to 8 Hz, then the mirrored half
+    let data = [9.0, 1.0, 3.0, 4.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 4.0, 3.0];
+    let mut bands = Vec::new();
+
+    settings.aggregate(&data, 1.0, &mut bands);
+    assert_eq!(bands, vec![1.0, 4.0]);
+
+    settings.aggregation = BandAggregation::Rms;
+    settings.aggregate(&data, 1.0, &mut bands);
+    assert_eq!(bands, vec![1.0, 12.5f32.sqrt()]);
+  }
+
+  #[test]
+  fn narrow_bands_use_the_closest_bin() {
+    let settings = BandSettings {
+      enabled: true,
+      bands_per_octave: 12,
+      min_hz: 100.0,
+      max_hz: 110.0,
+      ..BandSettings::default()
+    };
+
+    let mut bands = Vec::new();
+    settings.aggregate(&[0.0, 1.0, 2.0, 3.0, 4.0, 3.0, 2.0, 1.0], 50.0, &mut bands);
+
+    assert_eq!(bands, vec![2.0, 2.0]);
+  }
+}
@@ -0,0 +1,399 @@
+use std::sync::{Arc, Weak};
+use std::sync::mpsc::{sync_channel, Receiver, RecvError, RecvTimeoutError, SyncSender, TryIter, TryRecvError, TrySendError};
+use std::time::Duration;
+
+use crate::audio::AudioData;
+use crate::watchdog::Heartbeat;
+
+/// How many frames a subscription holds on to before new ones are dropped, or the sink waits for room
+pub const SUBSCRIPTION_CAPACITY: usize = 64;
+
+/// How often a sink waiting for room checks again
+const WAIT_POLL: Duration = Duration::from_millis(1);
+
+/// Frames kept around to be reused once every subscriber is done with them
+const POOL_SIZE: usize = SUBSCRIPTION_CAPACITY * 2;
+
+/// Receives every frame analyzed after [`Audio::subscribe`](crate::audio::Audio::subscribe),
+/// across source changes, until it's dropped.
+///
+/// Frames queue up until they're received, up to [`SUBSCRIPTION_CAPACITY`] of them. Once it's full,
+/// live sources drop new frames so a slow subscriber never blocks the audio thread,
+/// other sources like files wait until there's room again so no frame is missed.
+pub struct Subscription {
+  receiver: Receiver<Arc<AudioData>>,
+  /// Lets [`Broadcast`] forget about dropped subscriptions
+  _alive: Arc<()>,
+}
+
+impl Subscription {
+  /// Blocks until the next frame, fails once the [`Audio`](crate::audio::Audio) is dropped
+  pub fn recv(&self) -> Result<Arc<AudioData>, RecvError> {
+    self.receiver.recv()
+  }
+
+  pub fn recv_timeout(&self, timeout: Duration) -> Result<Arc<AudioData>, RecvTimeoutError> {
+    self.receiver.recv_timeout(timeout)
+  }
+
+  pub fn try_recv(&self) -> Result<Arc<AudioData>, TryRecvError> {
+    self.receiver.try_recv()
+  }
+
+  /// Every frame that's already there, without blocking
+  pub fn try_iter(&self) -> TryIter<'_, Arc<AudioData>> {
+    self.receiver.try_iter()
+  }
+
+  /// Only the newest frame that's already there, for consumers that don't care about the rest
+  pub fn latest(&self) -> Option<Arc<AudioData>> {
+    self.receiver.try_iter().last()
+  }
+}
+
+/// Receives every frame like a [`Subscription`], but as a [`Stream`](futures_core::Stream)
+/// so async consumers can await frames instead of polling for them, it's full at the same point
+#[cfg(feature = "async")]
+pub struct AudioStream {
+  receiver: tokio::sync::mpsc::Receiver<Arc<AudioData>>,
+  _alive: Arc<()>,
+}
+
+#[cfg(feature = "async")]
+impl AudioStream {
+  /// Waits for the next frame, `None` once the [`Audio`](crate::audio::Audio) is dropped
+  pub async fn recv(&mut self) -> Option<Arc<AudioData>> {
+    self.receiver.recv().await
+  }
+
+  /// Only the newest frame that's already there, for consumers that don't care about the rest
+  pub fn latest(&mut self) -> Option<Arc<AudioData>> {
+    std::iter::from_fn(|| self.receiver.try_recv().ok()).last()
+  }
+}
+
+#[cfg(feature = "async")]
+impl futures_core::Stream for AudioStream {
+  type Item = Arc<AudioData>;
+
+  fn poll_next(
+    mut self: std::pin::Pin<&mut Self>,
+    cx: &mut std::task::Context<'_>,
+  ) -> std::task::Poll<Option<Self::Item>> {
+    self.receiver.poll_recv(cx)
+  }
+}
+
+#[derive(Clone)]
+enum FrameSender {
+  Sync(SyncSender<Arc<AudioData>>),
+  #[cfg(feature = "async")]
+  Async(tokio::sync::mpsc::Sender<Arc<AudioData>>),
+}
+
+#[derive(Clone)]
+pub(crate) struct Subscriber {
+  sender: FrameSender,
+  alive: Weak<()>,
+}
+
+impl Subscriber {
+  fn is_alive(&self) -> bool {
+    self.alive.strong_count() > 0
+  }
+
+  /// Never blocks, hands the frame back if the queue is full, `Ok(false)` once the subscription is gone
+  fn try_send(&self, data: Arc<AudioData>) -> Result<bool, Arc<AudioData>> {
+    match &self.sender {
+      FrameSender::Sync(sender) => match sender.try_send(data) {
+        Ok(()) => Ok(true),
+        Err(TrySendError::Full(data)) => Err(data),
+        Err(TrySendError::Disconnected(_)) => Ok(false),
+      },
+      #[cfg(feature = "async")]
+      FrameSender::Async(sender) => match sender.try_send(data) {
+        Ok(()) => Ok(true),
+        Err(tokio::sync::mpsc::error::TrySendError::Full(data)) => Err(data),
+        Err(tokio::sync::mpsc::error::TrySendError::Closed(_)) => Ok(false),
+      },
+    }
+  }
+}
+
+/// Keeps track of subscriptions on the [`Audio`](crate::audio::Audio) side, so every new sink gets them
+pub(crate) struct Broadcast {
+  subscribers: Vec<Subscriber>,
+}
+
+impl Broadcast {
+  pub(crate) fn new() -> Self {
+    Self { subscribers: Vec::new() }
+  }
+
+  /// The subscriber still has to be given to the current sink
+  pub(crate) fn subscribe(&mut self) -> (Subscription, Subscriber) {
+    let (sender, receiver) = sync_channel(SUBSCRIPTION_CAPACITY);
+    let alive = Arc::new(());
+    let subscriber = self.add(FrameSender::Sync(sender), &alive);
+
+    let subscription = Subscription {
+      receiver,
+      _alive: alive,
+    };
+
+    (subscription, subscriber)
+  }
+
+  /// Same as [`subscribe`](Self::subscribe) but for an [`AudioStream`]
+  #[cfg(feature = "async")]
+  pub(crate) fn stream(&mut self) -> (AudioStream, Subscriber) {
+    let (sender, receiver) = tokio::sync::mpsc::channel(SUBSCRIPTION_CAPACITY);
+    let alive = Arc::new(());
+    let subscriber = self.add(FrameSender::Async(sender), &alive);
+
+    let stream = AudioStream {
+      receiver,
+      _alive: alive,
+    };
+
+    (stream, subscriber)
+  }
+
+  fn add(&mut self, sender: FrameSender, alive: &Arc<()>) -> Subscriber {
+    let subscriber = Subscriber {
+      sender,
+      alive: Arc::downgrade(alive),
+    };
+
+    self.subscribers.retain(Subscriber::is_alive);
+    self.subscribers.push(subscriber.clone());
+
+    subscriber
+  }
+
+  pub(crate) fn subscribers(&mut self) -> &[Subscriber] {
+    self.subscribers.retain(Subscriber::is_alive);
+    &self.subscribers
+  }
+}
+
+/// Sink side of a [`Broadcast`]
+pub(crate) struct BroadcastSender {
+  subscribers: Vec<Subscriber>,
+  /// Frames that were sent, reused once nobody else holds them
+  pool: Vec<Arc<AudioData>>,
+}
+
+impl BroadcastSender {
+  pub(crate) fn new() -> Self {
+    Self {
+      subscribers: Vec::new(),
+      pool: Vec::new(),
+    }
+  }
+
+  pub(crate) fn add(&mut self, subscriber: Subscriber) {
+    self.subscribers.push(subscriber);
+  }
+
+  /// Only copies the frame when someone is subscribed. Subscriptions that are full get their frame
+  /// dropped, unless there's a `wait` heartbeat, then it waits for room until that's closed.
+  pub(crate) fn send(&mut self, data: &AudioData, wait: Option<&Heartbeat>) {
+    if self.subscribers.is_empty() {
+      return;
+    }
+
+    let frame = self.frame(data);
+
+    self.subscribers.retain(|it| {
+      let mut frame = frame.clone();
+
+      loop {
+        match it.try_send(frame) {
+          Ok(alive) => return alive,
+          Err(full) => match wait {
+            Some(heartbeat) if !heartbeat.is_closed() => {
+              frame = full;
+              std::thread::sleep(WAIT_POLL);
+            }
+            _ => return true,
+          },
+        }
+      }
+    });
+  }
+
+  /// Copies `data` into a pooled frame nobody holds anymore, only allocates when they're all in use
+  fn frame(&mut self, data: &AudioData) -> Arc<AudioData> {
+    for frame in &mut self.pool {
+      if let Some(reused) = Arc::get_mut(frame) {
+        reused.clone_from(data);
+        return frame.clone();
+      }
+    }
+
+    let frame = Arc::new(data.clone());
+
+    if self.pool.len() < POOL_SIZE {
+      self.pool.push(frame.clone());
+    }
+
+    frame
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::Arc;
+
+  use crate::audio::{AudioData, AudioMode};
+  use crate::broadcast::{Broadcast, BroadcastSender, SUBSCRIPTION_CAPACITY};
+  use crate::watchdog::Heartbeat;
+  use crate::source::tests::{SliceSource, audio};
+
+  fn frame(value: f32) -> AudioData {
+    AudioData {
+      data: vec![value],
+      ..AudioData::default()
+    }
+  }
+
+  #[test]
+  fn every_subscriber_gets_every_frame() {
+    let mut broadcast = Broadcast::new();
+    let mut sender = BroadcastSender::new();
+    let (first, subscriber) = broadcast.subscribe();
+    sender.add(subscriber);
+    let (second, subscriber) = broadcast.subscribe();
+    sender.add(subscriber);
+
+    sender.send(&frame(1.0), None);
+    sender.send(&frame(2.0), None);
+
+    for subscription in [&first, &second] {
+      let frames: Vec<f32> = subscription.try_iter().map(|it| it.data[0]).collect();
+      assert_eq!(frames, vec![1.0, 2.0]);
+    }
+  }
+
+  #[test]
+  fn dropped_subscribers_are_forgotten() {
+    let mut broadcast = Broadcast::new();
+    let mut sender = BroadcastSender::new();
+    let (first, subscriber) = broadcast.subscribe();
+    sender.add(subscriber);
+
+    drop(first);
+    sender.send(&frame(1.0), None);
+    assert!(sender.subscribers.is_empty());
+
+    let (_second, _) = broadcast.subscribe();
+    assert_eq!(broadcast.subscribers().len(), 1);
+  }
+
+  #[test]
+  fn full_subscriptions_drop_new_frames() {
+    let mut broadcast = Broadcast::new();
+    let mut sender = BroadcastSender::new();
+    let (subscription, subscriber) = broadcast.subscribe();
+    sender.add(subscriber);
+
+    for it in 0..SUBSCRIPTION_CAPACITY + 10 {
+      sender.send(&frame(it as f32), None);
+    }
+
+    let frames: Vec<f32> = subscription.try_iter().map(|it| it.data[0]).collect();
+    assert_eq!(frames, (0..SUBSCRIPTION_CAPACITY).map(|it| it as f32).collect::<Vec<_>>());
+
+    // Still subscribed once there's room again
+    sender.send(&frame(-1.0), None);
+    assert_eq!(subscription.latest().map(|it| it.data[0]), Some(-1.0));
+  }
+
+  #[test]
+  fn full_subscriptions_can_be_waited_for() {
+    let mut broadcast = Broadcast::new();
+    let mut sender = BroadcastSender::new();
+    let (subscription, subscriber) = broadcast.subscribe();
+    sender.add(subscriber);
+
+    let count = SUBSCRIPTION_CAPACITY + 10;
+    let heartbeat = Arc::new(Heartbeat::new());
+    let thread = {
+      let heartbeat = heartbeat.clone();
+
+      std::thread::spawn(move || {
+        for it in 0..count {
+          sender.send(&frame(it as f32), Some(&heartbeat));
+        }
+
+        sender
+      })
+    };
+
+    let frames: Vec<f32> = (0..count).map(|_| subscription.recv().unwrap().data[0]).collect();
+    assert_eq!(frames, (0..count).map(|it| it as f32).collect::<Vec<_>>());
+
+    // Closing stops the waiting, even though nobody receives anymore
+    let mut sender = thread.join().unwrap();
+    heartbeat.close();
+
+    for it in 0..SUBSCRIPTION_CAPACITY + 1 {
+      sender.send(&frame(it as f32), Some(&heartbeat));
+    }
+  }
+
+  #[test]
+  fn received_frames_are_reused() {
+    let mut broadcast = Broadcast::new();
+    let mut sender = BroadcastSender::new();
+    let (subscription, subscriber) = broadcast.subscribe();
+    sender.add(subscriber);
+
+    sender.send(&frame(1.0), None);
+    let first = subscription.recv().unwrap();
+    let address = Arc::as_ptr(&first);
+
+    // Still held, so it can't be reused yet
+    sender.send(&frame(2.0), None);
+    let second = subscription.recv().unwrap();
+    assert_ne!(Arc::as_ptr(&second), address);
+
+    drop(first);
+    sender.send(&frame(3.0), None);
+    let third = subscription.recv().unwrap();
+
+    assert_eq!(Arc::as_ptr(&third), address);
+    assert_eq!(third.data, vec![3.0]);
+  }
+
+  #[test]
+  fn subscriptions_outlive_sources() {
+    let mut audio = audio(AudioMode::Wave);
+    let subscription = audio.subscribe();
+
+    audio.change_source(SliceSource(vec![1.0])).unwrap();
+    audio.change_source(SliceSource(vec![2.0])).unwrap();
+
+    let frames: Vec<Vec<f32>> = subscription.try_iter().map(|it| it.data.clone()).collect();
+    assert_eq!(frames, vec![vec![1.0], vec![2.0]]);
+  }
+
+  #[cfg(feature = "async")]
+  #[tokio::test]
+  async fn streams_get_every_frame() {
+    let mut broadcast = Broadcast::new();
+    let mut sender = BroadcastSender::new();
+    let (mut stream, subscriber) = broadcast.stream();
+    sender.add(subscriber);
+
+    sender.send(&frame(1.0), None);
+    sender.send(&frame(2.0), None);
+
+    assert_eq!(stream.recv().await.map(|it| it.data[0]), Some(1.0));
+    assert_eq!(stream.recv().await.map(|it| it.data[0]), Some(2.0));
+
+    drop((broadcast, sender));
+    assert!(stream.recv().await.is_none());
+  }
+}
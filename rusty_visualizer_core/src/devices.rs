@@ -0,0 +1,138 @@
+use std::ops::RangeInclusive;
+
+use cpal::{Device, Host, SampleFormat, SupportedStreamConfigRange};
+use cpal::traits::{DeviceTrait, HostTrait};
+
+use crate::audio::AudioDevice;
+use crate::error::AudioResult;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum DeviceDirection {
+  Input,
+  /// Captured through loopback
+  Output,
+}
+
+/// Everything a device picker needs to know about a device
+#[derive(Clone, PartialEq, Debug)]
+pub struct DeviceInfo {
+  pub name: String,
+  pub direction: DeviceDirection,
+  /// Opened by [`AudioDevice::Default`]
+  pub is_default: bool,
+  /// Opened by [`AudioDevice::Loopback`]
+  pub is_loopback: bool,
+  /// Supported channel counts, sorted
+  pub channels: Vec<u16>,
+  /// Supported sample rates, sorted and without overlaps
+  pub sample_rates: Vec<RangeInclusive<u32>>,
+  pub sample_formats: Vec<SampleFormat>,
+  pub host: &'static str,
+}
+
+impl DeviceInfo {
+  fn new(
+    host: &Host,
+    device: &Device,
+    direction: DeviceDirection,
+    default: &Option<String>,
+    loopback: &Option<String>,
+  ) -> Option<Self> {
+    let name = device.name().ok()?;
+    // Devices that are busy or otherwise can't be queried are still listed, just without capabilities
+    let configs: Vec<SupportedStreamConfigRange> = match direction {
+      DeviceDirection::Input => device.supported_input_configs().map(Iterator::collect),
+      DeviceDirection::Output => device.supported_output_configs().map(Iterator::collect),
+    }
+    .unwrap_or_default();
+
+    let mut channels: Vec<u16> = configs.iter().map(|it| it.channels()).collect();
+    channels.sort_unstable();
+    channels.dedup();
+
+    let mut sample_formats: Vec<SampleFormat> = Vec::new();
+
+    for config in &configs {
+      if !sample_formats.contains(&config.sample_format()) {
+        sample_formats.push(config.sample_format());
+      }
+    }
+
+    let sample_rates = merge_ranges(
+      configs
+        .iter()
+        .map(|it| it.min_sample_rate().0..=it.max_sample_rate().0)
+        .collect(),
+    );
+
+    Some(Self {
+      is_default: direction == DeviceDirection::Input && default.as_ref() == Some(&name),
+      is_loopback: direction == DeviceDirection::Output && loopback.as_ref() == Some(&name),
+      name,
+      direction,
+      channels,
+      sample_rates,
+      sample_formats,
+      host: host.id().name(),
+    })
+  }
+
+  pub fn supports_sample_rate(&self, sample_rate: u32) -> bool {
+    self.sample_rates.iter().any(|it| it.contains(&sample_rate))
+  }
+
+  /// The device to pass to [`Audio::change_device`](crate::audio::Audio::change_device)
+  pub fn to_audio_device(&self) -> AudioDevice<String> {
+    match self.direction {
+      DeviceDirection::Input => AudioDevice::Input(self.name.clone()),
+      DeviceDirection::Output => AudioDevice::Output(self.name.clone()),
+    }
+  }
+}
+
+/// Lists every input device, followed by every output device
+pub(crate) fn devices(host: &Host) -> AudioResult<Vec<DeviceInfo>> {
+  let default = host.default_input_device().and_then(|it| it.name().ok());
+  let loopback = host.default_output_device().and_then(|it| it.name().ok());
+  let mut devices = Vec::new();
+
+  for device in host.input_devices()? {
+    devices.extend(DeviceInfo::new(host, &device, DeviceDirection::Input, &default, &loopback));
+  }
+
+  for device in host.output_devices()? {
+    devices.extend(DeviceInfo::new(host, &device, DeviceDirection::Output, &default, &loopback));
+  }
+
+  Ok(devices)
+}
+
+fn merge_ranges(mut ranges: Vec<RangeInclusive<u32>>) -> Vec<RangeInclusive<u32>> {
+  ranges.sort_unstable_by_key(|it| *it.start());
+
+  let mut merged: Vec<RangeInclusive<u32>> = Vec::with_capacity(ranges.len());
+
+  for range in ranges {
+    match merged.last_mut() {
+      Some(last) if *range.start() <= last.end().saturating_add(1) => {
+        *last = *last.start()..=*last.end().max(range.end());
+      }
+      _ => merged.push(range),
+    }
+  }
+
+  merged
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::devices::merge_ranges;
+
+  #[test]
+  fn merges_overlapping_sample_rates() {
+    assert_eq!(
+      merge_ranges(vec![48000..=48000, 8000..=44100, 44100..=44100, 44101..=44101, 96000..=192000]),
+      vec![8000..=44101, 48000..=48000, 96000..=192000]
+    );
+  }
+}
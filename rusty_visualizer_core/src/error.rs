@@ -0,0 +1,77 @@
+use std::fmt::{Display, Formatter};
+
+use cpal::{BuildStreamError, DefaultStreamConfigError, DevicesError, PauseStreamError, PlayStreamError};
+use symphonia::core::errors::Error as DecodeError;
+
+#[derive(Debug)]
+pub enum AudioError {
+  DeviceNotFound(String),
+  /// Needs a source, but there isn't one
+  NoSource,
+  Devices(DevicesError),
+  UnsupportedConfig(DefaultStreamConfigError),
+  BuildStream(BuildStreamError),
+  PlayStream(PlayStreamError),
+  PauseStream(PauseStreamError),
+  Io(std::io::Error),
+  Decode(DecodeError),
+  Wav(hound::Error),
+}
+
+pub type AudioResult<T> = Result<T, AudioError>;
+
+impl Display for AudioError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      AudioError::DeviceNotFound(name) => write!(f, "Device not found: {}", name),
+      AudioError::NoSource => write!(f, "There isn't a source"),
+      AudioError::Devices(err) => write!(f, "Failed to list devices: {}", err),
+      AudioError::UnsupportedConfig(err) => write!(f, "Unsupported stream config: {}", err),
+      AudioError::BuildStream(err) => write!(f, "Failed to build stream: {}", err),
+      AudioError::PlayStream(err) => write!(f, "Failed to play stream: {}", err),
+      AudioError::PauseStream(err) => write!(f, "Failed to pause stream: {}", err),
+      AudioError::Io(err) => write!(f, "{}", err),
+      AudioError::Decode(err) => write!(f, "Failed to decode: {}", err),
+      AudioError::Wav(err) => write!(f, "Failed to write wav: {}", err),
+    }
+  }
+}
+
+impl std::error::Error for AudioError {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      AudioError::DeviceNotFound(_) | AudioError::NoSource => None,
+      AudioError::Devices(err) => Some(err),
+      AudioError::UnsupportedConfig(err) => Some(err),
+      AudioError::BuildStream(err) => Some(err),
+      AudioError::PlayStream(err) => Some(err),
+      AudioError::PauseStream(err) => Some(err),
+      AudioError::Io(err) => Some(err),
+      AudioError::Decode(err) => Some(err),
+      AudioError::Wav(err) => Some(err),
+    }
+  }
+}
+
+macro_rules! impl_from {
+  ($($variant:ident($error:ty)),* $(,)?) => {
+    $(
+      impl From<$error> for AudioError {
+        fn from(err: $error) -> Self {
+          AudioError::$variant(err)
+        }
+      }
+    )*
+  };
+}
+
+impl_from!(
+  Devices(DevicesError),
+  UnsupportedConfig(DefaultStreamConfigError),
+  BuildStream(BuildStreamError),
+  PlayStream(PlayStreamError),
+  PauseStream(PauseStreamError),
+  Io(std::io::Error),
+  Decode(DecodeError),
+  Wav(hound::Error),
+);
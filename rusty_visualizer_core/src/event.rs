@@ -0,0 +1,28 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Things noticed on the audio thread, see [`Audio::events`](crate::audio::Audio::events)
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum AudioEvent {
+  /// The source has been quiet for the silence hold time
+  SilenceStarted,
+  SilenceEnded,
+}
+
+/// Sink side of the events, shared by every sink an [`Audio`](crate::audio::Audio) creates
+/// so the state carries over when the source changes
+#[derive(Clone, Default)]
+pub(crate) struct EventSender {
+  silent: Arc<AtomicBool>,
+}
+
+impl EventSender {
+  pub(crate) fn is_silent(&self) -> bool {
+    self.silent.load(Ordering::Relaxed)
+  }
+
+  /// Only the state is kept, [`Audio::events`](crate::audio::Audio::events) turns it back into events
+  pub(crate) fn send(&self, event: AudioEvent) {
+    self.silent.store(event == AudioEvent::SilenceStarted, Ordering::Relaxed);
+  }
+}
@@ -1,3 +1,5 @@
+use std::f32::consts::PI;
+
 use num_complex::Complex32;
 use serde::Deserialize;
 use serde::Serialize;
@@ -96,3 +98,258 @@ pub fn process_fft(data: &mut [Complex32], size: &FFTSize, mode: FFTMode) {
     forward(data, size);
   }
 }
+
+/// FFT of a single size and direction, with everything it needs computed up front
+/// so [`process`](Self::process) and [`process_real`](Self::process_real) never allocate.
+///
+/// Same transform as [`process_fft`], which builds its twiddles up by multiplying them together,
+/// so the error adds up at bigger sizes that these exact ones don't have.
+pub struct FftPlan {
+  size: FFTSize,
+  mode: FFTMode,
+  /// Twiddle factors for every `k` below half the size
+  twiddles: Vec<Complex32>,
+  /// Real input packed into complex pairs
+  scratch: Vec<Complex32>,
+  /// Output of [`process_real`](Self::process_real)
+  spectrum: Vec<Complex32>,
+}
+
+impl FftPlan {
+  pub fn new(size: FFTSize, mode: FFTMode) -> Self {
+    let n = size as usize;
+    // `Backward` is what the analysis uses, and the one that uses negative exponents in `process_fft`
+    let sign = if mode == FFTMode::Forward { 1.0 } else { -1.0 };
+
+    let twiddles = (0..n / 2)
+      .map(|k| Complex32::from_polar(1.0, sign * 2.0 * PI * k as f32 / n as f32))
+      .collect();
+
+    Self {
+      size,
+      mode,
+      twiddles,
+      scratch: Vec::with_capacity(n / 2),
+      spectrum: Vec::with_capacity(n / 2 + 1),
+    }
+  }
+
+  pub fn size(&self) -> FFTSize {
+    self.size
+  }
+
+  pub fn mode(&self) -> FFTMode {
+    self.mode
+  }
+
+  /// Transforms the first `size` values of `data` in place, like [`process_fft`]
+  pub fn process(&self, data: &mut [Complex32]) {
+    let n = self.size as usize;
+    let data = &mut data[..n];
+
+    radix2(data, &self.twiddles, n);
+
+    if self.mode == FFTMode::Forward {
+      forward(data, n);
+    }
+  }
+
+  /// Transforms real `input`, zero padded or cut to `size`, with a complex FFT of half the size.
+  ///
+  /// Returns bins `0..=size / 2`, the rest mirror them.
+  pub fn process_real(&mut self, input: &[f32]) -> &[Complex32] {
+    let n = self.size as usize;
+    let half = n / 2;
+    let sample = |index: usize| input.get(index).copied().unwrap_or(0.0);
+
+    // Even samples go in the real part, odd ones in the imaginary part
+    self.scratch.clear();
+    self.scratch.extend((0..half).map(|it| Complex32::new(sample(it * 2), sample(it * 2 + 1))));
+
+    radix2(&mut self.scratch, &self.twiddles, n);
+
+    // Separates the transforms of the even and odd samples again, then combines them like a radix-2 step
+    let (scratch, twiddles) = (&self.scratch, &self.twiddles);
+
+    self.spectrum.clear();
+    self.spectrum.extend((0..=half).map(|k| {
+      let z = scratch[k % half];
+      let mirrored = scratch[(half - k) % half].conj();
+      let even = (z + mirrored) * 0.5;
+      let odd = (z - mirrored) * Complex32::new(0.0, -0.5);
+      let twiddle = match twiddles.get(k) {
+        Some(twiddle) => *twiddle,
+        // W^(n/2) is always -1
+        None => -Complex32::new(1.0, 0.0),
+      };
+
+      even + twiddle * odd
+    }));
+
+    if self.mode == FFTMode::Forward {
+      for bin in &mut self.spectrum {
+        *bin /= n as f32;
+      }
+    }
+
+    &self.spectrum
+  }
+}
+
+/// Keeps a [`FftPlan`] for every size and direction that was asked for
+#[derive(Default)]
+pub struct FftPlanner {
+  plans: Vec<FftPlan>,
+}
+
+impl FftPlanner {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Only allocates the first time a size and direction is planned
+  pub fn plan(&mut self, size: FFTSize, mode: FFTMode) -> &mut FftPlan {
+    let index = match self.plans.iter().position(|it| it.size == size && it.mode == mode) {
+      Some(index) => index,
+      None => {
+        self.plans.push(FftPlan::new(size, mode));
+        self.plans.len() - 1
+      }
+    };
+
+    &mut self.plans[index]
+  }
+}
+
+/// Iterative radix-2 FFT of `data`, where `twiddles` were made for a transform of `table` values,
+/// which can be bigger than `data` as long as both are powers of two
+fn radix2(data: &mut [Complex32], twiddles: &[Complex32], table: usize) {
+  let n = data.len();
+
+  if n < 2 {
+    return;
+  }
+
+  let bits = n.trailing_zeros();
+
+  for i in 0..n {
+    let j = i.reverse_bits() >> (usize::BITS - bits);
+
+    if i < j {
+      data.swap(i, j);
+    }
+  }
+
+  let mut half = 1;
+
+  while half < n {
+    let stride = table / (half * 2);
+
+    for start in (0..n).step_by(half * 2) {
+      for j in 0..half {
+        let a = start + j;
+        let b = a + half;
+        let product = data[b] * twiddles[j * stride];
+
+        data[b] = data[a] - product;
+        data[a] += product;
+      }
+    }
+
+    half *= 2;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use num_complex::Complex32;
+  use num_traits::Zero;
+
+  use crate::fft::{FFTMode, FFTSize, FftPlan, FftPlanner, process_fft};
+
+  fn signal(size: usize) -> Vec<f32> {
+    (0..size).map(|it| (it as f32 * 0.3).sin() + (it as f32 * 1.7).cos() * 0.5).collect()
+  }
+
+  /// Reference transform without any tricks
+  fn dft(input: &[f32], mode: FFTMode) -> Vec<Complex32> {
+    let n = input.len();
+    let sign = if mode == FFTMode::Forward { 1.0 } else { -1.0 };
+    let scale = if mode == FFTMode::Forward { n as f64 } else { 1.0 };
+
+    (0..n)
+      .map(|k| {
+        let (re, im) = input.iter().enumerate().fold((0.0, 0.0), |(re, im), (i, it)| {
+          let angle = sign * 2.0 * std::f64::consts::PI * ((k * i) % n) as f64 / n as f64;
+          (re + *it as f64 * angle.cos(), im + *it as f64 * angle.sin())
+        });
+
+        Complex32::new((re / scale) as f32, (im / scale) as f32)
+      })
+      .collect()
+  }
+
+  fn assert_close(left: Complex32, right: Complex32) {
+    assert!((left - right).norm() < 1e-3 * (1.0 + right.norm()), "{} != {}", left, right);
+  }
+
+  #[test]
+  fn plans_match_dft() {
+    for size in [FFTSize::FFT16, FFTSize::FFT1024] {
+      for mode in [FFTMode::Forward, FFTMode::Backward] {
+        let n = size as usize;
+        let expected = dft(&signal(n), mode);
+        let mut actual: Vec<Complex32> = signal(n).into_iter().map(Complex32::from).collect();
+
+        let mut plan = FftPlan::new(size, mode);
+        plan.process(&mut actual);
+
+        for (actual, expected) in actual.iter().zip(&expected) {
+          assert_close(*actual, *expected);
+        }
+
+        let spectrum = plan.process_real(&signal(n));
+        assert_eq!(spectrum.len(), n / 2 + 1);
+
+        for (actual, expected) in spectrum.iter().zip(&expected) {
+          assert_close(*actual, *expected);
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn process_fft_is_close_at_small_sizes() {
+    let size = FFTSize::FFT16;
+    let mut actual: Vec<Complex32> = signal(16).into_iter().map(Complex32::from).collect();
+    process_fft(&mut actual, &size, FFTMode::Backward);
+
+    for (actual, expected) in actual.iter().zip(&dft(&signal(16), FFTMode::Backward)) {
+      assert_close(*actual, *expected);
+    }
+  }
+
+  #[test]
+  fn real_input_is_zero_padded() {
+    let mut plan = FftPlan::new(FFTSize::FFT16, FFTMode::Backward);
+    let spectrum = plan.process_real(&[1.0]);
+
+    assert_eq!(spectrum.len(), 9);
+
+    for bin in spectrum {
+      assert_close(*bin, Complex32::new(1.0, 0.0));
+    }
+
+    assert!(plan.process_real(&[]).iter().all(Complex32::is_zero));
+  }
+
+  #[test]
+  fn plans_are_reused() {
+    let mut planner = FftPlanner::new();
+    planner.plan(FFTSize::FFT16, FFTMode::Backward);
+    planner.plan(FFTSize::FFT32, FFTMode::Backward);
+    planner.plan(FFTSize::FFT16, FFTMode::Backward);
+
+    assert_eq!(planner.plans.len(), 2);
+  }
+}
@@ -0,0 +1,131 @@
+/// Ring buffer that keeps the last `capacity` samples, no matter how they were chunked when pushed
+#[derive(Clone, Debug)]
+pub struct SampleHistory {
+  buffer: Vec<f32>,
+  /// Where the next sample is written
+  position: usize,
+  len: usize,
+}
+
+impl SampleHistory {
+  pub fn new(capacity: usize) -> Self {
+    Self {
+      buffer: vec![0.0; capacity],
+      position: 0,
+      len: 0,
+    }
+  }
+
+  pub fn capacity(&self) -> usize {
+    self.buffer.len()
+  }
+
+  pub fn len(&self) -> usize {
+    self.len
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  pub fn clear(&mut self) {
+    self.position = 0;
+    self.len = 0;
+  }
+
+  pub fn push(&mut self, samples: &[f32]) {
+    let capacity = self.capacity();
+
+    // Only the newest samples would survive anyway
+    let samples = &samples[samples.len().saturating_sub(capacity)..];
+    let first = samples.len().min(capacity - self.position);
+
+    self.buffer[self.position..self.position + first].copy_from_slice(&samples[..first]);
+    self.buffer[..samples.len() - first].copy_from_slice(&samples[first..]);
+
+    self.position = (self.position + samples.len()) % capacity.max(1);
+    self.len = (self.len + samples.len()).min(capacity);
+  }
+
+  /// Copies the newest `out.len()` samples into `out` oldest first,
+  /// the front is filled with zeros when there aren't enough samples yet
+  pub fn latest(&self, out: &mut [f32]) {
+    self.before(out, 0);
+  }
+
+  /// Same as [`latest`](Self::latest), but ignores the newest `offset` samples
+  pub fn before(&self, out: &mut [f32], offset: usize) {
+    let offset = offset.min(self.len);
+    let count = out.len().min(self.len - offset);
+    let (padding, out) = out.split_at_mut(out.len() - count);
+
+    padding.fill(0.0);
+
+    let start = (self.position + self.capacity() * 2 - count - offset) % self.capacity().max(1);
+    let first = count.min(self.capacity() - start);
+
+    out[..first].copy_from_slice(&self.buffer[start..start + first]);
+    out[first..].copy_from_slice(&self.buffer[..count - first]);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::history::SampleHistory;
+
+  fn latest(history: &SampleHistory, len: usize) -> Vec<f32> {
+    let mut out = vec![-1.0; len];
+    history.latest(&mut out);
+    out
+  }
+
+  #[test]
+  fn pads_front_until_full() {
+    let mut history = SampleHistory::new(4);
+    history.push(&[1.0, 2.0]);
+
+    assert_eq!(history.len(), 2);
+    assert_eq!(latest(&history, 4), vec![0.0, 0.0, 1.0, 2.0]);
+    assert_eq!(latest(&history, 1), vec![2.0]);
+  }
+
+  #[test]
+  fn wraps_around() {
+    let mut history = SampleHistory::new(4);
+
+    for i in 0..10 {
+      history.push(&[i as f32]);
+    }
+
+    assert_eq!(history.len(), 4);
+    assert_eq!(latest(&history, 4), vec![6.0, 7.0, 8.0, 9.0]);
+
+    history.push(&[10.0, 11.0, 12.0]);
+    assert_eq!(latest(&history, 4), vec![9.0, 10.0, 11.0, 12.0]);
+  }
+
+  #[test]
+  fn keeps_newest_of_large_pushes() {
+    let mut history = SampleHistory::new(3);
+    history.push(&[1.0]);
+    history.push(&[2.0, 3.0, 4.0, 5.0, 6.0]);
+
+    assert_eq!(latest(&history, 3), vec![4.0, 5.0, 6.0]);
+  }
+
+  #[test]
+  fn skips_newest_samples() {
+    let mut history = SampleHistory::new(4);
+    history.push(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+
+    let mut out = vec![-1.0; 3];
+    history.before(&mut out, 1);
+    assert_eq!(out, vec![2.0, 3.0, 4.0]);
+
+    history.before(&mut out, 2);
+    assert_eq!(out, vec![0.0, 2.0, 3.0]);
+
+    history.before(&mut out, 10);
+    assert_eq!(out, vec![0.0, 0.0, 0.0]);
+  }
+}
@@ -7,8 +7,25 @@ pub extern crate num_traits;
 // pub extern crate serde;
 // pub extern crate serde_json;
 
+pub mod agc;
 pub mod audio;
+pub mod bands;
+pub mod broadcast;
+pub mod devices;
+pub mod error;
+pub mod event;
 pub mod fft;
+pub mod history;
 pub mod iterator;
+pub mod recorder;
+pub mod scale;
 pub mod settings;
+pub mod silence;
+pub mod sink;
+pub mod source;
+pub mod stft;
+pub mod stream;
+pub mod triple_buffer;
 pub mod util;
+pub mod watchdog;
+pub mod window;
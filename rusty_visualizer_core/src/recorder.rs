@@ -0,0 +1,203 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use hound::{SampleFormat, WavSpec, WavWriter};
+
+use crate::error::AudioResult;
+use crate::source::AudioFormat;
+
+/// How often the writer checks if it should stop while no samples arrive
+const STOP_POLL: Duration = Duration::from_millis(100);
+
+/// A wav file being written from the samples a source pushes, exactly as they arrive
+pub struct Recording {
+  path: PathBuf,
+  stopped: Arc<AtomicBool>,
+  thread: Option<JoinHandle<AudioResult<()>>>,
+}
+
+/// Sink side of a [`Recording`], only hands samples over so the audio thread never touches the file
+pub(crate) struct RecorderTap {
+  sender: Sender<(AudioFormat, Vec<f32>)>,
+  /// Buffers the writer is done with, so they aren't allocated again every push
+  recycled: Receiver<Vec<f32>>,
+  stopped: Arc<AtomicBool>,
+}
+
+impl Recording {
+  /// Creates the file right away, it's only written once samples arrive through the tap
+  pub(crate) fn create(path: impl AsRef<Path>) -> AudioResult<(Self, RecorderTap)> {
+    let path = path.as_ref().to_path_buf();
+    let file = BufWriter::new(File::create(&path)?);
+    let stopped = Arc::new(AtomicBool::new(false));
+    let (sender, receiver) = channel();
+    let (recycle, recycled) = channel();
+
+    let thread = {
+      let stopped = stopped.clone();
+      let path = path.clone();
+
+      std::thread::spawn(move || {
+        let result = write_wav(file, receiver, recycle, &stopped);
+
+        // Nothing arrived, so there isn't even a header
+        if let Ok(false) = result {
+          let _ = std::fs::remove_file(path);
+        }
+
+        result.map(|_| ())
+      })
+    };
+
+    let recording = Self {
+      path,
+      stopped: stopped.clone(),
+      thread: Some(thread),
+    };
+
+    let tap = RecorderTap {
+      sender,
+      recycled,
+      stopped,
+    };
+
+    Ok((recording, tap))
+  }
+
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+
+  /// Writes whatever is left and finishes the file
+  pub fn stop(mut self) -> AudioResult<()> {
+    self.finish()
+  }
+
+  fn finish(&mut self) -> AudioResult<()> {
+    self.stopped.store(true, Ordering::SeqCst);
+
+    match self.thread.take() {
+      Some(thread) => thread.join().unwrap_or(Ok(())),
+      None => Ok(()),
+    }
+  }
+}
+
+impl Drop for Recording {
+  fn drop(&mut self) {
+    if let Err(err) = self.finish() {
+      println!("{}", err);
+    }
+  }
+}
+
+impl RecorderTap {
+  /// Returns `false` once the recording stopped and the tap can be dropped
+  pub(crate) fn write(&self, format: AudioFormat, data: &[f32]) -> bool {
+    if self.stopped.load(Ordering::Relaxed) {
+      return false;
+    }
+
+    let mut buffer = self.recycled.try_recv().unwrap_or_default();
+    buffer.clear();
+    buffer.extend_from_slice(data);
+
+    self.sender.send((format, buffer)).is_ok()
+  }
+}
+
+/// Returns whether anything was written
+fn write_wav(
+  file: BufWriter<File>,
+  receiver: Receiver<(AudioFormat, Vec<f32>)>,
+  recycle: Sender<Vec<f32>>,
+  stopped: &AtomicBool,
+) -> AudioResult<bool> {
+  let mut file = Some(file);
+  let mut writer: Option<(AudioFormat, WavWriter<BufWriter<File>>)> = None;
+
+  loop {
+    let (format, samples) = match receiver.recv_timeout(STOP_POLL) {
+      Ok(it) => it,
+      // Samples that were pushed before stopping still get written
+      Err(RecvTimeoutError::Timeout) if !stopped.load(Ordering::SeqCst) => continue,
+      Err(_) => break,
+    };
+
+    // The header is only known once the first samples arrive
+    if let Some(file) = file.take() {
+      let spec = WavSpec {
+        channels: format.channels,
+        sample_rate: format.sample_rate,
+        bits_per_sample: 32,
+        sample_format: SampleFormat::Float,
+      };
+
+      writer = Some((format, WavWriter::new(file, spec)?));
+    }
+
+    match &mut writer {
+      // A wav file can't change formats halfway through
+      Some((first, _)) if *first != format => {
+        println!("Stopped recording, the format changed from {:?} to {:?}", first, format);
+        break;
+      }
+      Some((_, writer)) => {
+        for sample in &samples {
+          writer.write_sample(*sample)?;
+        }
+      }
+      None => break,
+    }
+
+    let _ = recycle.send(samples);
+  }
+
+  match writer {
+    Some((_, writer)) => writer.finalize()?,
+    None => return Ok(false),
+  }
+
+  Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::{Arc, Mutex};
+
+  use crate::audio::AudioMode;
+  use crate::source::tests::{SharedSource, audio};
+
+  #[test]
+  fn records_pushed_samples() {
+    let path = std::env::temp_dir().join("rusty_visualizer_records_pushed_samples.wav");
+    let mut audio = audio(AudioMode::Wave);
+
+    assert!(audio.start_recording(&path).is_err());
+
+    let sink = Arc::new(Mutex::new(None));
+    audio.change_source(SharedSource(sink.clone())).unwrap();
+    audio.start_recording(&path).unwrap();
+
+    if let Some(sink) = sink.lock().unwrap().as_mut() {
+      sink.push(&[0.25, -0.5]);
+      sink.push(&[1.0]);
+    }
+
+    audio.stop_recording().unwrap();
+
+    let mut reader = hound::WavReader::open(&path).unwrap();
+    let samples: Vec<f32> = reader.samples::<f32>().map(Result::unwrap).collect();
+
+    assert_eq!(reader.spec().sample_rate, 44100);
+    assert_eq!(samples, vec![0.25, -0.5, 1.0]);
+
+    std::fs::remove_file(path).unwrap();
+  }
+}
@@ -0,0 +1,108 @@
+use num_complex::Complex32;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// How FFT bins are turned into the values in [`AudioData`](crate::audio::AudioData).
+///
+/// Every scale except [`FourthRoot`](Self::FourthRoot) is normalized by the FFT size, so a full scale
+/// sine is 1 (0 dB) no matter the size. Windows are already normalized by their gain when applied.
+#[derive(Copy, Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum MagnitudeScale {
+  /// How it always was, not normalized and roughly between 0 and 1 at 1024
+  #[default]
+  FourthRoot,
+  /// Amplitude of the frequency
+  Linear,
+  /// Amplitude squared
+  Power,
+  /// dBFS, where `floor` is 0 and `ceiling` is 1, anything outside of them is clamped
+  Decibel { floor: f32, ceiling: f32 },
+}
+
+impl MagnitudeScale {
+  pub const ALL: &'static [Self] = &[
+    MagnitudeScale::FourthRoot,
+    MagnitudeScale::Linear,
+    MagnitudeScale::Power,
+    MagnitudeScale::Decibel {
+      floor: -90.0,
+      ceiling: 0.0,
+    },
+  ];
+
+  pub const fn name(&self) -> &'static str {
+    match self {
+      MagnitudeScale::FourthRoot => "Fourth Root",
+      MagnitudeScale::Linear => "Linear",
+      MagnitudeScale::Power => "Power",
+      MagnitudeScale::Decibel { .. } => "Decibel",
+    }
+  }
+
+  /// Whether both are the same scale, ignoring parameters
+  pub fn is_same_kind(&self, other: &Self) -> bool {
+    std::mem::discriminant(self) == std::mem::discriminant(other)
+  }
+
+  /// Value of `bin` at `index` in an FFT of `size`
+  pub fn apply(&self, bin: Complex32, index: usize, size: usize) -> f32 {
+    let magnitude = bin.norm();
+    let amplitude = || {
+      // Every other bin shares its energy with the one mirroring it
+      let shared = if index == 0 || index * 2 == size { 1.0 } else { 2.0 };
+      magnitude * shared / size.max(1) as f32
+    };
+
+    match *self {
+      MagnitudeScale::FourthRoot => magnitude.sqrt() / 10f32,
+      MagnitudeScale::Linear => amplitude(),
+      MagnitudeScale::Power => amplitude().powi(2),
+      MagnitudeScale::Decibel { floor, ceiling } => {
+        let db = 20.0 * amplitude().max(f32::MIN_POSITIVE).log10();
+        let range = (ceiling - floor).max(f32::EPSILON);
+
+        ((db - floor) / range).clamp(0.0, 1.0)
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::f32::consts::PI;
+
+  use crate::audio::{AudioData, AudioMode};
+  use crate::fft::{FFTSize, FftPlanner};
+  use crate::scale::MagnitudeScale;
+  use crate::source::AudioFormat;
+
+  /// Peak of a full scale sine exactly on bin 8
+  fn peak(size: FFTSize, scale: MagnitudeScale) -> f32 {
+    let n = size as usize;
+    let samples: Vec<f32> = (0..n).map(|it| (2.0 * PI * 8.0 * it as f32 / n as f32).sin()).collect();
+    let mut data = AudioData::default();
+    data.analyze(&samples, AudioMode::FFT(size), AudioFormat::default(), scale, &mut FftPlanner::new());
+
+    data.data[8]
+  }
+
+  #[test]
+  fn full_scale_is_the_same_at_every_size() {
+    for size in [FFTSize::FFT64, FFTSize::FFT1024, FFTSize::FFT16384] {
+      assert!((peak(size, MagnitudeScale::Linear) - 1.0).abs() < 1e-3);
+      assert!((peak(size, MagnitudeScale::Power) - 1.0).abs() < 1e-3);
+    }
+  }
+
+  #[test]
+  fn decibels_are_mapped_between_floor_and_ceiling() {
+    let scale = MagnitudeScale::Decibel {
+      floor: -60.0,
+      ceiling: 20.0,
+    };
+
+    assert!((peak(FFTSize::FFT1024, scale) - 0.75).abs() < 1e-3);
+    assert_eq!(scale.apply(0.0.into(), 8, 1024), 0.0);
+    assert_eq!(scale.apply(10000.0.into(), 8, 1024), 1.0);
+  }
+}
@@ -5,13 +5,44 @@ use serde::de::DeserializeOwned;
 use serde::Deserialize;
 use serde::Serialize;
 
-use crate::audio::{Audio, AudioDevice, AudioMode, ToSerializableAudioDevice};
+use crate::agc::AgcSettings;
+use crate::audio::{Audio, AudioDevice, AudioMode, ChannelMode, PlaybackState, ToSerializableAudioDevice};
+use crate::bands::BandSettings;
+use crate::error::AudioResult;
+use crate::scale::MagnitudeScale;
+use crate::silence::SilenceSettings;
+use crate::stft::StftSettings;
+use crate::stream::StreamSettings;
+use crate::watchdog::{SourceStatus, WatchdogSettings};
+use crate::window::WindowFunction;
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct AudioSettings {
   pub device: AudioDevice<String>,
   pub mode: AudioMode,
-  pub auto_play: bool,
+  #[serde(default)]
+  pub channel_mode: ChannelMode,
+  #[serde(default)]
+  pub watchdog: WatchdogSettings,
+  #[serde(default)]
+  pub stream: StreamSettings,
+  #[serde(default)]
+  pub agc: AgcSettings,
+  #[serde(default)]
+  pub silence: SilenceSettings,
+  /// Only used in [`AudioMode::FFT`]
+  #[serde(default)]
+  pub window: WindowFunction,
+  #[serde(default)]
+  pub stft: StftSettings,
+  /// Only used in [`AudioMode::FFT`]
+  #[serde(default)]
+  pub scale: MagnitudeScale,
+  #[serde(default)]
+  pub bands: BandSettings,
+  /// What sources do as soon as they start, [`AudioManager::change_state`] keeps it up to date
+  #[serde(default)]
+  pub state: PlaybackState,
   pub auto_set: bool,
 }
 
@@ -20,8 +51,17 @@ impl AudioSettings {
     AudioSettings {
       device,
       mode: AudioMode::Wave,
-      auto_play: true,
+      channel_mode: ChannelMode::Mono,
+      state: PlaybackState::Playing,
       auto_set: true,
+      watchdog: WatchdogSettings::default(),
+      stream: StreamSettings::default(),
+      agc: AgcSettings::default(),
+      silence: SilenceSettings::default(),
+      window: WindowFunction::default(),
+      stft: StftSettings::default(),
+      scale: MagnitudeScale::default(),
+      bands: BandSettings::default(),
     }
   }
 }
@@ -74,10 +114,77 @@ pub trait AudioManager {
     self.audio().change_mode(new_mode);
   }
 
-  fn change_device(&mut self, new_device: impl ToSerializableAudioDevice) {
+  fn change_channel_mode(&mut self, new_mode: ChannelMode) {
+    self.audio_settings().channel_mode = new_mode;
+    self.audio().change_channel_mode(new_mode);
+  }
+
+  fn change_agc(&mut self, agc: AgcSettings) {
+    self.audio_settings().agc = agc;
+    self.audio().change_agc(agc);
+  }
+
+  fn change_silence(&mut self, silence: SilenceSettings) {
+    self.audio_settings().silence = silence;
+    self.audio().change_silence(silence);
+  }
+
+  fn change_window(&mut self, window: WindowFunction) {
+    self.audio_settings().window = window;
+    self.audio().change_window(window);
+  }
+
+  fn change_stft(&mut self, stft: StftSettings) {
+    self.audio_settings().stft = stft;
+    self.audio().change_stft(stft);
+  }
+
+  fn change_scale(&mut self, scale: MagnitudeScale) {
+    self.audio_settings().scale = scale;
+    self.audio().change_scale(scale);
+  }
+
+  fn change_bands(&mut self, bands: BandSettings) {
+    self.audio_settings().bands = bands;
+    self.audio().change_bands(bands);
+  }
+
+  /// Also makes it start in `state` the next time
+  fn change_state(&mut self, state: PlaybackState) -> AudioResult<()> {
+    self.audio_settings().state = state;
+    self.audio().change_state(state)
+  }
+
+  fn toggle_playing(&mut self) -> AudioResult<()> {
+    if self.audio().is_playing() {
+      self.change_state(PlaybackState::Paused)
+    } else {
+      self.change_state(PlaybackState::Playing)
+    }
+  }
+
+  fn change_device(&mut self, new_device: impl ToSerializableAudioDevice) -> AudioResult<()> {
     let new_device = new_device.to_serializable(self.audio());
 
-    self.audio_settings().device = new_device.clone();
-    self.audio().change_device(new_device);
+    self.audio_settings().device = new_device;
+
+    let settings = self.audio_settings().clone();
+    self.audio().open_device(&settings)
+  }
+
+  /// Reopens the configured device with the new settings
+  fn change_stream_settings(&mut self, stream: StreamSettings) -> AudioResult<()> {
+    self.audio_settings().stream = stream;
+    self.audio().change_stream_settings(stream);
+
+    let settings = self.audio_settings().clone();
+    self.audio().open_device(&settings)
+  }
+
+  /// Reopens the configured device when it stops working, see [`Audio::watch`]
+  fn watch(&mut self) -> SourceStatus {
+    let settings = self.audio_settings().clone();
+
+    self.audio().watch(&settings).clone()
   }
 }
\ No newline at end of file
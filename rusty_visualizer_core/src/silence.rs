@@ -0,0 +1,105 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::event::AudioEvent;
+
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SilenceSettings {
+  /// RMS level below which it counts as quiet, the default is about -60 dB
+  pub threshold: f32,
+  /// Seconds it has to stay quiet before it's silent
+  pub hold: f32,
+}
+
+impl Default for SilenceSettings {
+  fn default() -> Self {
+    Self {
+      threshold: 0.001,
+      hold: 2.0,
+    }
+  }
+}
+
+/// Notices when samples stay under a threshold for a while, and when they come back
+pub struct SilenceDetector {
+  settings: SilenceSettings,
+  silent: bool,
+  /// Seconds it's been quiet for
+  quiet: f32,
+}
+
+impl SilenceDetector {
+  pub fn new(settings: SilenceSettings, silent: bool) -> Self {
+    Self {
+      settings,
+      silent,
+      quiet: 0.0,
+    }
+  }
+
+  pub fn settings(&self) -> SilenceSettings {
+    self.settings
+  }
+
+  pub fn set_settings(&mut self, settings: SilenceSettings) {
+    self.settings = settings;
+  }
+
+  pub fn is_silent(&self) -> bool {
+    self.silent
+  }
+
+  /// Takes samples that last `duration` seconds, returns an event when the state changes
+  pub fn process(&mut self, samples: &[f32], duration: f32) -> Option<AudioEvent> {
+    if samples.is_empty() {
+      return None;
+    }
+
+    let rms = (samples.iter().map(|it| it * it).sum::<f32>() / samples.len() as f32).sqrt();
+
+    if rms >= self.settings.threshold {
+      self.quiet = 0.0;
+
+      if self.silent {
+        self.silent = false;
+        return Some(AudioEvent::SilenceEnded);
+      }
+    } else {
+      self.quiet += duration;
+
+      if !self.silent && self.quiet >= self.settings.hold {
+        self.silent = true;
+        return Some(AudioEvent::SilenceStarted);
+      }
+    }
+
+    None
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::event::AudioEvent;
+  use crate::silence::{SilenceDetector, SilenceSettings};
+
+  #[test]
+  fn silence_starts_after_hold_and_ends_right_away() {
+    let mut detector = SilenceDetector::new(SilenceSettings::default(), false);
+    let quiet = [0.0001; 480];
+    let loud = [0.5; 480];
+
+    // A quarter of a second at a time, so 2 seconds is 8 pushes
+    for _ in 0..7 {
+      assert_eq!(detector.process(&quiet, 0.25), None);
+    }
+
+    assert_eq!(detector.process(&quiet, 0.25), Some(AudioEvent::SilenceStarted));
+    assert_eq!(detector.process(&quiet, 0.25), None);
+    assert!(detector.is_silent());
+
+    assert_eq!(detector.process(&loud, 0.25), Some(AudioEvent::SilenceEnded));
+    assert_eq!(detector.process(&loud, 0.25), None);
+    assert!(!detector.is_silent());
+  }
+}
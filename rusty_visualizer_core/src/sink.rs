@@ -0,0 +1,413 @@
+use std::sync::Arc;
+use std::sync::mpsc::Receiver;
+use std::time::{Duration, Instant};
+
+use crossbeam_utils::atomic::AtomicCell;
+
+use crate::agc::{Agc, AgcSettings};
+use crate::audio::{AudioData, AudioMode, ChannelMode};
+use crate::bands::BandSettings;
+use crate::broadcast::{BroadcastSender, Subscriber};
+use crate::event::EventSender;
+use crate::fft::{FFTSize, FftPlanner};
+use crate::history::SampleHistory;
+use crate::recorder::RecorderTap;
+use crate::scale::MagnitudeScale;
+use crate::silence::{SilenceDetector, SilenceSettings};
+use crate::source::AudioFormat;
+use crate::stft::{Stft, StftSettings};
+use crate::triple_buffer::TripleBufferInput;
+use crate::watchdog::Heartbeat;
+use crate::window::{Window, WindowFunction};
+
+pub(crate) const MAX_FFT_SIZE: usize = FFTSize::FFT16384 as usize;
+
+/// Sent from [`Audio`](crate::audio::Audio) to the sink of its current source, picked up on the next push
+pub(crate) enum SinkCommand {
+  Subscribe(Subscriber),
+  Record(RecorderTap),
+  Agc(AgcSettings),
+  Silence(SilenceSettings),
+  Window(WindowFunction),
+  Stft(StftSettings),
+  Scale(MagnitudeScale),
+  Bands(BandSettings),
+}
+
+/// Receiving end of an [`AudioSource`](crate::source::AudioSource), turns raw samples into [`AudioData`].
+///
+/// Everything is preallocated, so pushing never allocates unless the format, channel mode, FFT size
+/// or the amount of samples pushed at once changes, or subscribers hold on to more frames than they can queue.
+/// It never blocks either, except for sinks of sources that aren't live, which wait for full subscriptions.
+pub struct AudioSink {
+  mode: Arc<AtomicCell<AudioMode>>,
+  channel_mode: Arc<AtomicCell<ChannelMode>>,
+  sender: TripleBufferInput<AudioData>,
+  commands: Receiver<SinkCommand>,
+  broadcast: BroadcastSender,
+  /// Waits for full subscriptions instead of dropping frames
+  wait_for_subscribers: bool,
+  recorder: Option<RecorderTap>,
+  events: EventSender,
+  silence: SilenceDetector,
+  agc: Agc,
+  /// Samples after gain control
+  gained: Vec<f32>,
+  format: AudioFormat,
+  mixed: Channel,
+  /// Every channel on its own, only filled with [`ChannelMode::Split`]
+  channels: Vec<Channel>,
+  analyzer: Analyzer,
+  stft: Stft,
+  heartbeat: Arc<Heartbeat>,
+  /// Frames analyzed so far
+  count: u64,
+}
+
+/// Buffers shared by every channel's analysis
+struct Analyzer {
+  frame: Vec<f32>,
+  window: Window,
+  scale: MagnitudeScale,
+  bands: BandSettings,
+  planner: FftPlanner,
+}
+
+/// Samples of a single channel from the last push and everything before it
+struct Channel {
+  samples: Vec<f32>,
+  history: SampleHistory,
+}
+
+impl Channel {
+  fn new() -> Self {
+    Self {
+      samples: Vec::with_capacity(MAX_FFT_SIZE),
+      history: SampleHistory::new(MAX_FFT_SIZE),
+    }
+  }
+
+  fn extend(&mut self, samples: impl Iterator<Item = f32>) {
+    self.samples.clear();
+    self.samples.extend(samples);
+    self.history.push(&self.samples);
+  }
+
+  fn analyze(
+    &self,
+    result: &mut AudioData,
+    mode: AudioMode,
+    format: AudioFormat,
+    offset: usize,
+    analyzer: &mut Analyzer,
+  ) {
+    match mode {
+      AudioMode::Wave => {
+        result.analyze(&self.samples, mode, format, analyzer.scale, &mut analyzer.planner);
+        result.bands.clear();
+      }
+      // Analyze the `size` samples ending `offset` before the newest so the whole window is real audio,
+      // not zero padding
+      AudioMode::FFT(size) => {
+        let frame = &mut analyzer.frame[..size as usize];
+        self.history.before(frame, offset);
+        analyzer.window.apply(frame);
+
+        result.analyze(frame, mode, format, analyzer.scale, &mut analyzer.planner);
+
+        match (analyzer.bands.enabled, result.bin_width()) {
+          (true, Some(width)) => analyzer.bands.aggregate(&result.data, width, &mut result.bands),
+          _ => result.bands.clear(),
+        }
+      }
+    }
+  }
+}
+
+impl AudioSink {
+  pub(crate) fn new(
+    mode: Arc<AtomicCell<AudioMode>>,
+    channel_mode: Arc<AtomicCell<ChannelMode>>,
+    sender: TripleBufferInput<AudioData>,
+    commands: Receiver<SinkCommand>,
+    events: EventSender,
+    wait_for_subscribers: bool,
+  ) -> Self {
+    Self {
+      mode,
+      channel_mode,
+      sender,
+      commands,
+      broadcast: BroadcastSender::new(),
+      wait_for_subscribers,
+      recorder: None,
+      silence: SilenceDetector::new(SilenceSettings::default(), events.is_silent()),
+      events,
+      agc: Agc::new(AgcSettings::default()),
+      gained: Vec::with_capacity(MAX_FFT_SIZE),
+      format: AudioFormat::default(),
+      mixed: Channel::new(),
+      channels: Vec::new(),
+      analyzer: Analyzer {
+        frame: vec![0.0; MAX_FFT_SIZE],
+        window: Window::new(WindowFunction::default()),
+        scale: MagnitudeScale::default(),
+        bands: BandSettings::default(),
+        planner: FftPlanner::new(),
+      },
+      stft: Stft::new(StftSettings::default()),
+      heartbeat: Arc::new(Heartbeat::new()),
+      count: 0,
+    }
+  }
+
+  /// Lets sources report errors from places the sink can't go, like a stream's error callback
+  pub fn heartbeat(&self) -> Arc<Heartbeat> {
+    self.heartbeat.clone()
+  }
+
+  pub fn format(&self) -> AudioFormat {
+    self.format
+  }
+
+  /// Describes the samples given to [`push`](Self::push) from now on
+  pub fn set_format(&mut self, format: AudioFormat) {
+    if self.format != format {
+      self.format = format;
+      self.mixed.history.clear();
+      self.channels.clear();
+    }
+  }
+
+  /// Analyzes interleaved samples in the format given by [`set_format`](Self::set_format),
+  /// the newest one having just been captured
+  pub fn push(&mut self, data: &[f32]) {
+    let frames = data.len() / self.format.channels.max(1) as usize;
+    self.push_captured(data, self.duration(frames));
+  }
+
+  /// Same as [`push`](Self::push) for sources that know the first sample was captured `latency` ago
+  pub fn push_captured(&mut self, data: &[f32], latency: Duration) {
+    let callback = Instant::now();
+
+    while let Ok(command) = self.commands.try_recv() {
+      match command {
+        SinkCommand::Subscribe(subscriber) => self.broadcast.add(subscriber),
+        SinkCommand::Record(recorder) => self.recorder = Some(recorder),
+        SinkCommand::Agc(settings) => self.agc.set_settings(settings),
+        SinkCommand::Silence(settings) => self.silence.set_settings(settings),
+        SinkCommand::Window(function) => self.analyzer.window.set_function(function),
+        SinkCommand::Stft(settings) => self.stft.set_settings(settings),
+        SinkCommand::Scale(scale) => self.analyzer.scale = scale,
+        SinkCommand::Bands(settings) => self.analyzer.bands = settings,
+      }
+    }
+
+    if let Some(recorder) = &self.recorder {
+      if !recorder.write(self.format, data) {
+        self.recorder = None;
+      }
+    }
+
+    let mode = self.mode.load();
+    let channel_mode = self.channel_mode.load();
+    let channels = self.format.channels.max(1) as usize;
+    let frames = data.len() / channels;
+
+    // Before gain control, which would make silence loud
+    if let Some(event) = self.silence.process(data, frames as f32 / self.format.sample_rate.max(1) as f32) {
+      self.events.send(event);
+    }
+
+    let data = if self.agc.settings().enabled {
+      self.gained.clear();
+      self.gained.extend_from_slice(data);
+      self.agc.process(&mut self.gained, frames, self.format.sample_rate);
+
+      &self.gained[..]
+    } else {
+      data
+    };
+
+    self.mixed.extend(data.chunks_exact(channels).map(|it| channel_mode.mix(it)));
+
+    if channel_mode == ChannelMode::Split {
+      self.channels.resize_with(channels, Channel::new);
+
+      for (index, channel) in self.channels.iter_mut().enumerate() {
+        channel.extend(data.iter().skip(index).step_by(channels).copied());
+      }
+    } else {
+      self.channels.clear();
+    }
+
+    // Frames are timed by when their newest sample was captured
+    let newest = callback.checked_sub(latency).unwrap_or(callback) + self.duration(frames);
+
+    match mode {
+      AudioMode::FFT(size) if self.stft.settings().enabled => {
+        let size = size as usize;
+        self.stft.push(frames, MAX_FFT_SIZE - size);
+
+        while let Some(offset) = self.stft.next_frame(size) {
+          let capture = newest.checked_sub(self.duration(offset)).unwrap_or(newest);
+          self.publish(mode, offset, callback, capture);
+        }
+      }
+      _ => self.publish(mode, 0, callback, newest),
+    }
+
+    self.heartbeat.beat();
+  }
+
+  /// How long `frames` last at the current sample rate
+  fn duration(&self, frames: usize) -> Duration {
+    Duration::from_secs_f64(frames as f64 / self.format.sample_rate.max(1) as f64)
+  }
+
+  /// Analyzes a frame ending `offset` samples before the newest one and hands it out
+  fn publish(&mut self, mode: AudioMode, offset: usize, callback: Instant, capture: Instant) {
+    let result = self.sender.input_buffer();
+    self.count += 1;
+
+    self.mixed.analyze(result, mode, self.format, offset, &mut self.analyzer);
+    result.channels.resize_with(self.channels.len(), || AudioData::with_capacity(MAX_FFT_SIZE));
+
+    for (channel, result) in self.channels.iter().zip(&mut result.channels) {
+      channel.analyze(result, mode, self.format, offset, &mut self.analyzer);
+    }
+
+    result.frame = self.count;
+    result.callback = callback;
+    result.capture = capture;
+
+    let wait = self.wait_for_subscribers.then_some(&*self.heartbeat);
+    self.broadcast.send(result, wait);
+    self.sender.publish();
+  }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+  use std::sync::Arc;
+  use std::sync::mpsc::{channel, Sender};
+  use std::time::Duration;
+
+  use crossbeam_utils::atomic::AtomicCell;
+
+  use crate::audio::{AudioData, AudioMode, ChannelMode};
+  use crate::event::EventSender;
+  use crate::fft::FFTSize;
+  use crate::sink::{AudioSink, SinkCommand};
+  use crate::source::AudioFormat;
+  use crate::stft::StftSettings;
+  use crate::triple_buffer::{triple_buffer, TripleBufferOutput};
+
+  /// Sink for 44100 Hz stereo without a source, commands sent to it are picked up on the next push
+  pub(crate) fn stereo_sink(
+    mode: AudioMode,
+    channel_mode: ChannelMode,
+  ) -> (AudioSink, TripleBufferOutput<AudioData>, Sender<SinkCommand>) {
+    let (sender, receiver) = triple_buffer(AudioData::default);
+    let (commands, command_receiver) = channel();
+    let mode = Arc::new(AtomicCell::new(mode));
+    let channel_mode = Arc::new(AtomicCell::new(channel_mode));
+    let mut sink = AudioSink::new(mode, channel_mode, sender, command_receiver, EventSender::default(), false);
+
+    sink.set_format(AudioFormat {
+      channels: 2,
+      sample_rate: 44100,
+    });
+
+    (sink, receiver, commands)
+  }
+
+  fn push_stereo(channel_mode: ChannelMode, data: &[f32]) -> AudioData {
+    let (mut sink, mut receiver, _) = stereo_sink(AudioMode::Wave, channel_mode);
+    sink.push(data);
+
+    receiver.read().clone()
+  }
+
+  #[test]
+  fn settings_are_lock_free() {
+    assert!(AtomicCell::<AudioMode>::is_lock_free());
+    assert!(AtomicCell::<ChannelMode>::is_lock_free());
+  }
+
+  #[test]
+  fn mixes_channels() {
+    let data = [1.0, 0.5, -1.0, 0.5];
+
+    assert_eq!(push_stereo(ChannelMode::Mono, &data).data, vec![0.75, -0.25]);
+    assert_eq!(push_stereo(ChannelMode::Left, &data).data, vec![1.0, -1.0]);
+    assert_eq!(push_stereo(ChannelMode::Right, &data).data, vec![0.5, 0.5]);
+    assert_eq!(push_stereo(ChannelMode::Side, &data).data, vec![0.25, -0.75]);
+  }
+
+  #[test]
+  fn splits_channels() {
+    let data = push_stereo(ChannelMode::Split, &[1.0, 0.5, -1.0, 0.5]);
+
+    assert_eq!(data.data, vec![0.75, -0.25]);
+    assert_eq!(data.channels.len(), 2);
+    assert_eq!(data.channels[0].data, vec![1.0, -1.0]);
+    assert_eq!(data.channels[1].data, vec![0.5, 0.5]);
+  }
+
+  #[test]
+  fn frames_are_counted_and_timed() {
+    let (mut sink, mut receiver, _) = stereo_sink(AudioMode::Wave, ChannelMode::Mono);
+    let latency = Duration::from_millis(5);
+
+    sink.push(&[0.0, 0.0]);
+    assert_eq!(receiver.read().frame, 1);
+
+    sink.push_captured(&[0.0, 0.0], latency);
+    let data = receiver.read();
+    let capture = data.callback - latency + Duration::from_secs_f64(1.0 / 44100.0);
+
+    assert_eq!(data.frame, 2);
+    assert_eq!(data.capture, capture);
+    assert!(data.age() >= data.callback - capture);
+  }
+
+  #[test]
+  fn overlapping_frames_are_analyzed_every_hop() {
+    let (mut sink, mut receiver, commands) = stereo_sink(AudioMode::FFT(FFTSize::FFT16), ChannelMode::Mono);
+
+    let _ = commands.send(SinkCommand::Stft(StftSettings {
+      enabled: true,
+      overlap: 0.5,
+    }));
+
+    // 20 stereo frames
+    sink.push(&[0.0; 40]);
+    assert_eq!(receiver.read().frame, 2);
+
+    sink.push(&[0.0; 8]);
+    assert_eq!(receiver.read().frame, 3);
+  }
+
+  #[test]
+  fn overlapping_frames_are_timed_by_their_end() {
+    let (mut sink, mut receiver, commands) = stereo_sink(AudioMode::FFT(FFTSize::FFT16), ChannelMode::Mono);
+
+    sink.set_format(AudioFormat {
+      channels: 1,
+      sample_rate: 1000,
+    });
+
+    let _ = commands.send(SinkCommand::Stft(StftSettings {
+      enabled: true,
+      overlap: 0.5,
+    }));
+
+    // 20 ms of samples, the last frame ends 4 samples before the newest one
+    sink.push_captured(&[0.0; 20], Duration::from_millis(20));
+    let data = receiver.read();
+
+    assert_eq!(data.frame, 2);
+    assert_eq!(data.capture, data.callback - Duration::from_millis(20) + Duration::from_millis(16));
+  }
+}
@@ -0,0 +1,150 @@
+pub use crate::sink::AudioSink;
+use crate::error::AudioResult;
+
+pub use self::device::DeviceSource;
+pub use self::file::{FileSettings, FileSource};
+pub use self::generator::{Generator, GeneratorSettings, GeneratorSource, Signal};
+pub use self::pipe::{PcmFormat, PipeSettings, PipeSource};
+
+mod device;
+mod file;
+mod generator;
+mod pipe;
+
+/// Something that produces samples for [`Audio`](crate::audio::Audio) to analyze,
+/// cpal devices being only one of them.
+pub trait AudioSource: Send {
+  fn name(&self) -> String;
+
+  /// Starts producing samples, every chunk of samples must be given to `sink`,
+  /// interleaved and described by [`AudioSink::set_format`] if it isn't mono
+  fn start(&mut self, sink: AudioSink) -> AudioResult<()>;
+
+  /// Live sources keep pushing while playing, they're reopened when they stop if
+  /// [`WatchdogSettings::timeout`](crate::watchdog::WatchdogSettings::timeout) is set
+  fn is_live(&self) -> bool {
+    false
+  }
+
+  fn play(&mut self) -> AudioResult<()> {
+    Ok(())
+  }
+
+  fn pause(&mut self) -> AudioResult<()> {
+    Ok(())
+  }
+}
+
+/// Layout of the samples given to [`AudioSink::push`]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct AudioFormat {
+  pub channels: u16,
+  pub sample_rate: u32,
+}
+
+impl Default for AudioFormat {
+  fn default() -> Self {
+    Self {
+      channels: 1,
+      sample_rate: 44100,
+    }
+  }
+}
+
+impl<S: AudioSource + ?Sized> AudioSource for Box<S> {
+  fn name(&self) -> String {
+    (**self).name()
+  }
+
+  fn start(&mut self, sink: AudioSink) -> AudioResult<()> {
+    (**self).start(sink)
+  }
+
+  fn is_live(&self) -> bool {
+    (**self).is_live()
+  }
+
+  fn play(&mut self) -> AudioResult<()> {
+    (**self).play()
+  }
+
+  fn pause(&mut self) -> AudioResult<()> {
+    (**self).pause()
+  }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+  use std::sync::{Arc, Mutex};
+
+  use crate::audio::{Audio, AudioMode};
+  use crate::error::AudioResult;
+  use crate::fft::FFTSize;
+  use crate::settings::AudioSettings;
+  use crate::source::{AudioSink, AudioSource};
+
+  /// Pushes all of its samples once when started
+  pub(crate) struct SliceSource(pub(crate) Vec<f32>);
+
+  impl AudioSource for SliceSource {
+    fn name(&self) -> String {
+      String::from("slice")
+    }
+
+    fn start(&mut self, mut sink: AudioSink) -> AudioResult<()> {
+      sink.push(&self.0);
+      Ok(())
+    }
+  }
+
+  /// Hands the sink to the test, so it can push whenever
+  pub(crate) struct SharedSource(pub(crate) Arc<Mutex<Option<AudioSink>>>);
+
+  impl AudioSource for SharedSource {
+    fn name(&self) -> String {
+      String::from("shared")
+    }
+
+    fn start(&mut self, sink: AudioSink) -> AudioResult<()> {
+      *self.0.lock().unwrap() = Some(sink);
+      Ok(())
+    }
+  }
+
+  pub(crate) fn audio(mode: AudioMode) -> Audio {
+    Audio::from(&AudioSettings {
+      mode,
+      auto_set: false,
+      ..AudioSettings::default()
+    })
+  }
+
+  #[test]
+  fn wave_passes_samples_through() {
+    let mut audio = audio(AudioMode::Wave);
+    audio.change_source(SliceSource(vec![0.25, -0.5, 1.0])).unwrap();
+
+    let data = audio.data().unwrap();
+    assert_eq!(data.data, vec![0.25, -0.5, 1.0]);
+    assert_eq!(data.sum, 0.75);
+  }
+
+  #[test]
+  fn fft_outputs_one_value_per_bin() {
+    let mut audio = audio(AudioMode::FFT(FFTSize::FFT64));
+    audio.change_source(SliceSource(vec![1.0; 64])).unwrap();
+
+    let data = audio.data().unwrap();
+    assert_eq!(data.len(), 64);
+    assert!(data[0] > data[1]);
+  }
+
+  #[test]
+  fn no_source_has_no_data() {
+    let mut audio = audio(AudioMode::Wave);
+    audio.change_source(SliceSource(vec![1.0])).unwrap();
+    audio.remove_source();
+
+    assert!(audio.data().is_none());
+  }
+}
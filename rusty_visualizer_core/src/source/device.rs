@@ -0,0 +1,147 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use cpal::{BufferSize, BuildStreamError, Device, InputCallbackInfo, Sample, SampleFormat, Stream, StreamConfig, StreamError, SupportedStreamConfig};
+use cpal::traits::{DeviceTrait, StreamTrait};
+
+use crate::error::AudioResult;
+use crate::sink::MAX_FFT_SIZE;
+use crate::source::{AudioFormat, AudioSink, AudioSource};
+use crate::watchdog::Heartbeat;
+
+unsafe impl Send for DeviceSource {}
+
+/// Captures audio from a cpal input device, or the output device when it's loopback
+pub struct DeviceSource {
+  device: Device,
+  config: SupportedStreamConfig,
+  buffer_size: BufferSize,
+  stream: Option<Stream>,
+}
+
+impl DeviceSource {
+  pub fn new(config: SupportedStreamConfig, device: Device) -> Self {
+    Self {
+      device,
+      config,
+      buffer_size: BufferSize::Default,
+      stream: None,
+    }
+  }
+
+  pub fn with_buffer_size(mut self, buffer_size: BufferSize) -> Self {
+    self.buffer_size = buffer_size;
+    self
+  }
+
+  pub fn device(&self) -> &Device {
+    &self.device
+  }
+
+  pub fn config(&self) -> &SupportedStreamConfig {
+    &self.config
+  }
+
+  /// Config the stream is built with
+  pub fn stream_config(&self) -> StreamConfig {
+    StreamConfig {
+      buffer_size: self.buffer_size.clone(),
+      ..self.config.config()
+    }
+  }
+
+  pub fn stream(&self) -> &Option<Stream> {
+    &self.stream
+  }
+
+  /// Builds a stream for devices that don't use f32 samples, converting them before they reach the sink
+  fn build_converted_stream<T: Sample>(&self, mut sink: AudioSink) -> Result<Stream, BuildStreamError> {
+    // Allocated up front, growing it in the callback could block the audio thread
+    let frames = match self.buffer_size {
+      BufferSize::Fixed(frames) => frames as usize,
+      BufferSize::Default => MAX_FFT_SIZE,
+    };
+    let mut buffer = Vec::<f32>::with_capacity(frames * self.config.channels() as usize);
+    let on_error = on_error(sink.heartbeat());
+
+    self.device.build_input_stream(
+      &self.stream_config(),
+      move |data: &[T], info: &InputCallbackInfo| {
+        buffer.clear();
+        buffer.extend(data.iter().map(Sample::to_f32));
+        sink.push_captured(&buffer, latency(info));
+      },
+      on_error,
+    )
+  }
+}
+
+/// How long before the callback the first sample was captured, zero if the host doesn't say
+fn latency(info: &InputCallbackInfo) -> Duration {
+  let timestamp = info.timestamp();
+
+  timestamp.callback.duration_since(&timestamp.capture).unwrap_or_default()
+}
+
+/// Errors usually mean the device is gone, so the watchdog gets to reopen it
+fn on_error(heartbeat: Arc<Heartbeat>) -> impl FnMut(StreamError) + Send + 'static {
+  move |err| heartbeat.fail(err)
+}
+
+impl AudioSource for DeviceSource {
+  fn name(&self) -> String {
+    self.device.name().unwrap_or_default()
+  }
+
+  fn is_live(&self) -> bool {
+    true
+  }
+
+  fn start(&mut self, mut sink: AudioSink) -> AudioResult<()> {
+    sink.set_format(AudioFormat {
+      channels: self.config.channels(),
+      sample_rate: self.config.sample_rate().0,
+    });
+
+    // Streams are built on their own thread, otherwise it conflicts with the window on some platforms
+    crossbeam_utils::thread::scope(|s| {
+      s.spawn(|_| {
+        let stream = match self.config.sample_format() {
+          SampleFormat::F32 => {
+            let on_error = on_error(sink.heartbeat());
+
+            self.device.build_input_stream(
+              &self.stream_config(),
+              move |data: &[f32], info: &InputCallbackInfo| sink.push_captured(data, latency(info)),
+              on_error,
+            )
+          }
+          SampleFormat::I16 => self.build_converted_stream::<i16>(sink),
+          SampleFormat::U16 => self.build_converted_stream::<u16>(sink),
+        }?;
+
+        self.stream = Some(stream);
+        Ok(())
+      })
+      .join()
+      .unwrap()
+    })
+      .unwrap()
+  }
+
+  fn play(&mut self) -> AudioResult<()> {
+    if let Some(stream) = &self.stream {
+      stream.play()?;
+    }
+
+    Ok(())
+  }
+
+  fn pause(&mut self) -> AudioResult<()> {
+    if let Some(stream) = &self.stream {
+      stream.pause()?;
+    }
+
+    Ok(())
+  }
+}
@@ -0,0 +1,447 @@
+use std::fs::File;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use serde::Serialize;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{CODEC_TYPE_NULL, Decoder, DecoderOptions};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, FormatReader};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+use crate::error::AudioResult;
+use crate::source::{AudioFormat, AudioSink, AudioSource};
+
+/// How many frames are pushed to the sink at once
+const CHUNK_FRAMES: usize = 1024;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FileSettings {
+  pub path: PathBuf,
+  /// Plays the file at its own sample rate, otherwise it's decoded as fast as possible
+  #[serde(default = "default_realtime")]
+  pub realtime: bool,
+  #[serde(default)]
+  pub looping: bool,
+}
+
+fn default_realtime() -> bool {
+  true
+}
+
+impl FileSettings {
+  pub fn new(path: impl Into<PathBuf>) -> Self {
+    Self {
+      path: path.into(),
+      realtime: true,
+      looping: false,
+    }
+  }
+}
+
+/// Decodes WAV, FLAC, OGG/Vorbis or MP3 files and feeds them to the sink
+pub struct FileSource {
+  settings: FileSettings,
+  playing: Arc<AtomicBool>,
+  stopped: Arc<AtomicBool>,
+  thread: Option<JoinHandle<()>>,
+}
+
+impl FileSource {
+  pub fn new(settings: FileSettings) -> Self {
+    Self {
+      settings,
+      playing: Arc::default(),
+      stopped: Arc::default(),
+      thread: None,
+    }
+  }
+
+  pub fn settings(&self) -> &FileSettings {
+    &self.settings
+  }
+}
+
+impl AudioSource for FileSource {
+  fn name(&self) -> String {
+    self.settings.path.display().to_string()
+  }
+
+  fn start(&mut self, mut sink: AudioSink) -> AudioResult<()> {
+    let settings = self.settings.clone();
+    let playing = self.playing.clone();
+    let stopped = self.stopped.clone();
+    // Opened here so missing or unsupported files are reported to the caller
+    let decoder = FileDecoder::open(&settings)?;
+
+    self.thread = Some(std::thread::spawn(move || {
+      let mut player = FilePlayer {
+        settings: &settings,
+        playing: &playing,
+        stopped: &stopped,
+      };
+
+      if let Err(err) = player.run(decoder, &mut sink) {
+        println!("{:?}", err);
+      }
+    }));
+
+    Ok(())
+  }
+
+  fn play(&mut self) -> AudioResult<()> {
+    self.playing.store(true, Ordering::SeqCst);
+    Ok(())
+  }
+
+  fn pause(&mut self) -> AudioResult<()> {
+    self.playing.store(false, Ordering::SeqCst);
+    Ok(())
+  }
+}
+
+impl Drop for FileSource {
+  fn drop(&mut self) {
+    self.stopped.store(true, Ordering::SeqCst);
+
+    if let Some(thread) = self.thread.take() {
+      let _ = thread.join();
+    }
+  }
+}
+
+struct FileDecoder {
+  format: Box<dyn FormatReader>,
+  decoder: Box<dyn Decoder>,
+  track_id: u32,
+  sample_rate: u32,
+  buffer: Option<SampleBuffer<f32>>,
+}
+
+impl FileDecoder {
+  fn open(settings: &FileSettings) -> Result<Self, SymphoniaError> {
+    let file = File::open(&settings.path)?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+
+    if let Some(extension) = settings.path.extension().and_then(|it| it.to_str()) {
+      hint.with_extension(extension);
+    }
+
+    let probed = symphonia::default::get_probe().format(
+      &hint,
+      stream,
+      &FormatOptions::default(),
+      &MetadataOptions::default(),
+    )?;
+
+    let format = probed.format;
+    let track = format
+      .tracks()
+      .iter()
+      .find(|it| it.codec_params.codec != CODEC_TYPE_NULL)
+      .ok_or(SymphoniaError::Unsupported("no supported audio track"))?;
+
+    let decoder = symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+    let track_id = track.id;
+    let sample_rate = track.codec_params.sample_rate.unwrap_or(44100);
+
+    Ok(Self {
+      format,
+      decoder,
+      track_id,
+      sample_rate,
+      buffer: None,
+    })
+  }
+
+  /// Decodes the next packet into interleaved samples and their channel count,
+  /// returns `None` at the end of the file
+  fn next(&mut self) -> Result<Option<(&[f32], usize)>, SymphoniaError> {
+    loop {
+      let packet = match self.format.next_packet() {
+        Ok(packet) => packet,
+        Err(SymphoniaError::IoError(err)) if err.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err),
+      };
+
+      if packet.track_id() != self.track_id {
+        continue;
+      }
+
+      let decoded = match self.decoder.decode(&packet) {
+        Ok(decoded) => decoded,
+        // Corrupted packets are skipped rather than stopping playback
+        Err(SymphoniaError::DecodeError(_)) => continue,
+        Err(err) => return Err(err),
+      };
+
+      let spec = *decoded.spec();
+      let channels = spec.channels.count();
+      let frames = decoded.capacity();
+
+      if !matches!(&self.buffer, Some(it) if it.capacity() >= frames * channels) {
+        self.buffer = Some(SampleBuffer::new(frames as u64, spec));
+      }
+
+      let buffer = self.buffer.as_mut().unwrap();
+      buffer.copy_interleaved_ref(decoded);
+
+      return Ok(Some((buffer.samples(), channels)));
+    }
+  }
+}
+
+struct FilePlayer<'a> {
+  settings: &'a FileSettings,
+  playing: &'a AtomicBool,
+  stopped: &'a AtomicBool,
+}
+
+impl FilePlayer<'_> {
+  fn run(&mut self, mut decoder: FileDecoder, sink: &mut AudioSink) -> Result<(), SymphoniaError> {
+    loop {
+      let frames = self.play_once(&mut decoder, sink)?;
+
+      // Files without any samples would be reopened as fast as possible
+      if !self.settings.looping || frames == 0 || self.stopped.load(Ordering::SeqCst) {
+        return Ok(());
+      }
+
+      decoder = FileDecoder::open(self.settings)?;
+    }
+  }
+
+  /// Returns how many frames were pushed
+  fn play_once(&mut self, decoder: &mut FileDecoder, sink: &mut AudioSink) -> Result<usize, SymphoniaError> {
+    let sample_rate = decoder.sample_rate as f64;
+    let mut start = Instant::now();
+    let mut frames = 0usize;
+
+    while let Some((samples, channels)) = decoder.next()? {
+      sink.set_format(AudioFormat {
+        channels: channels as u16,
+        sample_rate: sample_rate as u32,
+      });
+
+      for chunk in samples.chunks(CHUNK_FRAMES * channels) {
+        if !self.playing.load(Ordering::SeqCst) {
+          while !self.playing.load(Ordering::SeqCst) {
+            if self.stopped.load(Ordering::SeqCst) {
+              return Ok(frames);
+            }
+
+            std::thread::sleep(Duration::from_millis(10));
+          }
+
+          // Pick up where it left off instead of catching up on the time spent paused
+          start = Instant::now() - Duration::from_secs_f64(frames as f64 / sample_rate);
+        }
+
+        if self.stopped.load(Ordering::SeqCst) {
+          return Ok(frames);
+        }
+
+        sink.push(chunk);
+        frames += chunk.len() / channels;
+
+        if self.settings.realtime {
+          let due = start + Duration::from_secs_f64(frames as f64 / sample_rate);
+          let now = Instant::now();
+
+          if due > now {
+            std::thread::sleep(due - now);
+          }
+        }
+      }
+    }
+
+    Ok(frames)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::path::{Path, PathBuf};
+  use std::sync::atomic::AtomicBool;
+  use std::sync::mpsc::channel;
+  use std::time::Duration;
+
+  use hound::{SampleFormat, WavSpec, WavWriter};
+
+  use crate::audio::{Audio, AudioMode, ChannelMode};
+  use crate::broadcast::SUBSCRIPTION_CAPACITY;
+  use crate::sink::tests::stereo_sink;
+  use crate::source::{FileSettings, FileSource};
+  use crate::source::file::{CHUNK_FRAMES, FileDecoder, FilePlayer};
+  use crate::source::tests::audio;
+
+  const FRAMES: usize = 3000;
+
+  /// Stereo ramp from 0 to 1, the same on both channels so mixing doesn't change it
+  fn write_wav(name: &str) -> PathBuf {
+    write_frames(name, FRAMES)
+  }
+
+  fn write_frames(name: &str, frames: usize) -> PathBuf {
+    let path = std::env::temp_dir().join(name);
+    let spec = WavSpec {
+      channels: 2,
+      sample_rate: 48000,
+      bits_per_sample: 32,
+      sample_format: SampleFormat::Float,
+    };
+
+    let mut writer = WavWriter::create(&path, spec).unwrap();
+
+    for frame in 0..frames {
+      let sample = frame as f32 / frames as f32;
+      writer.write_sample(sample).unwrap();
+      writer.write_sample(sample).unwrap();
+    }
+
+    writer.finalize().unwrap();
+    path
+  }
+
+  /// Plays `path` as fast as possible, collecting samples until there are `count` or nothing arrives for a while
+  fn play(path: &Path, looping: bool, count: usize) -> (Audio, Vec<f32>) {
+    let mut audio = audio(AudioMode::Wave);
+    let subscription = audio.subscribe();
+    let settings = FileSettings {
+      path: path.to_path_buf(),
+      realtime: false,
+      looping,
+    };
+
+    audio.change_source(FileSource::new(settings)).unwrap();
+
+    let mut samples = Vec::new();
+
+    while samples.len() < count {
+      match subscription.recv_timeout(Duration::from_secs(1)) {
+        Ok(data) => {
+          assert_eq!(data.sample_rate, 48000);
+          assert_eq!(data.channel_count, 2);
+          samples.extend_from_slice(&data.data);
+        }
+        Err(_) => break,
+      }
+    }
+
+    (audio, samples)
+  }
+
+  #[test]
+  fn plays_every_sample() {
+    let path = write_wav("rusty_visualizer_plays_every_sample.wav");
+    let (_audio, samples) = play(&path, false, usize::MAX);
+
+    assert_eq!(samples.len(), FRAMES);
+    assert_eq!(samples[0], 0.0);
+    assert_eq!(samples[FRAMES - 1], (FRAMES - 1) as f32 / FRAMES as f32);
+
+    std::fs::remove_file(path).unwrap();
+  }
+
+  #[test]
+  fn looping_restarts() {
+    let path = write_wav("rusty_visualizer_looping_restarts.wav");
+    let (audio, samples) = play(&path, true, FRAMES * 2 + 1);
+
+    // Stops the decoder before the file is removed
+    drop(audio);
+
+    assert!(samples.len() > FRAMES * 2);
+    assert_eq!(samples[FRAMES], 0.0);
+    assert_eq!(samples[FRAMES * 2 - 1], samples[FRAMES - 1]);
+
+    std::fs::remove_file(path).unwrap();
+  }
+
+  #[test]
+  fn slow_subscribers_miss_nothing() {
+    let frames = CHUNK_FRAMES * (SUBSCRIPTION_CAPACITY + 10);
+    let path = write_frames("rusty_visualizer_slow_subscribers_miss_nothing.wav", frames);
+    let mut audio = audio(AudioMode::Wave);
+    let subscription = audio.subscribe();
+    let settings = FileSettings {
+      path: path.clone(),
+      realtime: false,
+      looping: false,
+    };
+
+    audio.change_source(FileSource::new(settings)).unwrap();
+
+    // Lets the subscription fill up
+    std::thread::sleep(Duration::from_millis(100));
+
+    let received: usize = std::iter::from_fn(|| subscription.recv_timeout(Duration::from_secs(1)).ok())
+      .map(|it| it.data.len())
+      .sum();
+
+    assert_eq!(received, frames);
+
+    drop(audio);
+    std::fs::remove_file(path).unwrap();
+  }
+
+  #[test]
+  fn full_subscriptions_dont_block_dropping() {
+    let path = write_frames("rusty_visualizer_full_subscriptions_dont_block_dropping.wav", CHUNK_FRAMES * (SUBSCRIPTION_CAPACITY + 10));
+    let (done, dropped) = channel();
+    let mut audio = audio(AudioMode::Wave);
+    let subscription = audio.subscribe();
+    let settings = FileSettings {
+      path: path.clone(),
+      realtime: false,
+      looping: false,
+    };
+
+    audio.change_source(FileSource::new(settings)).unwrap();
+
+    std::thread::spawn(move || {
+      // Never received from
+      let _subscription = subscription;
+      std::thread::sleep(Duration::from_millis(100));
+
+      drop(audio);
+      let _ = done.send(());
+    });
+
+    assert!(dropped.recv_timeout(Duration::from_secs(1)).is_ok());
+    std::fs::remove_file(path).unwrap();
+  }
+
+  #[test]
+  fn empty_files_stop_looping() {
+    let path = write_frames("rusty_visualizer_empty_files_stop_looping.wav", 0);
+    let settings = FileSettings {
+      path: path.clone(),
+      realtime: false,
+      looping: true,
+    };
+    let (done, finished) = channel();
+
+    std::thread::spawn(move || {
+      let (mut sink, _, _) = stereo_sink(AudioMode::Wave, ChannelMode::Mono);
+      let mut player = FilePlayer {
+        settings: &settings,
+        playing: &AtomicBool::new(true),
+        stopped: &AtomicBool::new(false),
+      };
+
+      let _ = done.send(player.run(FileDecoder::open(&settings).unwrap(), &mut sink).is_ok());
+    });
+
+    assert_eq!(finished.recv_timeout(Duration::from_secs(1)), Ok(true));
+
+    std::fs::remove_file(path).unwrap();
+  }
+}
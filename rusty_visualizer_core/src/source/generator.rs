@@ -0,0 +1,384 @@
+use std::f64::consts::TAU;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::error::AudioResult;
+use crate::source::{AudioFormat, AudioSink, AudioSource};
+
+/// How many samples are generated and pushed to the sink at once
+const BLOCK_SIZE: usize = 512;
+/// Longest the generator thread sleeps before checking if it should stop
+const STOP_POLL: Duration = Duration::from_millis(10);
+/// Lowest frequency a sweep can go, its frequency is multiplied so it can't start at 0
+const MIN_SWEEP_FREQUENCY: f32 = 1.0;
+/// Shortest a sweep can be in seconds
+const MIN_SWEEP_DURATION: f32 = 0.001;
+
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Signal {
+  Sine { frequency: f32 },
+  Square { frequency: f32 },
+  /// Logarithmic sweep from `start` to `end` hz over `duration` seconds, then starts over
+  Sweep { start: f32, end: f32, duration: f32 },
+  WhiteNoise,
+  PinkNoise,
+  /// Single sample impulses, `frequency` times a second
+  Impulse { frequency: f32 },
+}
+
+impl Signal {
+  pub const DEFAULTS: &'static [Self] = &[
+    Signal::Sine { frequency: 440.0 },
+    Signal::Square { frequency: 440.0 },
+    Signal::Sweep { start: 20.0, end: 20000.0, duration: 10.0 },
+    Signal::WhiteNoise,
+    Signal::PinkNoise,
+    Signal::Impulse { frequency: 4.0 },
+  ];
+
+  pub const fn name(&self) -> &'static str {
+    match self {
+      Signal::Sine { .. } => "Sine",
+      Signal::Square { .. } => "Square",
+      Signal::Sweep { .. } => "Sweep",
+      Signal::WhiteNoise => "White Noise",
+      Signal::PinkNoise => "Pink Noise",
+      Signal::Impulse { .. } => "Impulse",
+    }
+  }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GeneratorSettings {
+  pub signal: Signal,
+  #[serde(default = "default_sample_rate")]
+  pub sample_rate: u32,
+  #[serde(default = "default_amplitude")]
+  pub amplitude: f32,
+}
+
+fn default_sample_rate() -> u32 {
+  48000
+}
+
+fn default_amplitude() -> f32 {
+  0.5
+}
+
+impl GeneratorSettings {
+  pub fn new(signal: Signal) -> Self {
+    Self {
+      signal,
+      sample_rate: default_sample_rate(),
+      amplitude: default_amplitude(),
+    }
+  }
+
+  /// Settings that can't divide by zero, for ones that come from a settings file
+  pub fn clamped(&self) -> Self {
+    let signal = match self.signal {
+      Signal::Sweep { start, end, duration } => Signal::Sweep {
+        start: start.max(MIN_SWEEP_FREQUENCY),
+        end: end.max(MIN_SWEEP_FREQUENCY),
+        duration: duration.max(MIN_SWEEP_DURATION),
+      },
+      signal => signal,
+    };
+
+    Self {
+      signal,
+      sample_rate: self.sample_rate.max(1),
+      amplitude: self.amplitude,
+    }
+  }
+}
+
+/// Endless iterator of samples for a [`Signal`]
+pub struct Generator {
+  settings: GeneratorSettings,
+  /// Position within the current period, between 0 and 1
+  phase: f64,
+  /// How many samples have been generated so far
+  sample: u64,
+  seed: u32,
+  pink: [f32; 7],
+}
+
+impl Generator {
+  /// `settings` are [`clamped`](GeneratorSettings::clamped)
+  pub fn new(settings: GeneratorSettings) -> Self {
+    Self {
+      settings: settings.clamped(),
+      phase: 0.0,
+      sample: 0,
+      seed: 0x9E37_79B9,
+      pink: [0.0; 7],
+    }
+  }
+
+  pub fn settings(&self) -> &GeneratorSettings {
+    &self.settings
+  }
+
+  pub fn fill(&mut self, buffer: &mut [f32]) {
+    for (sample, value) in buffer.iter_mut().zip(self) {
+      *sample = value;
+    }
+  }
+
+  /// xorshift32, good enough for noise and doesn't need another dependency
+  fn white(&mut self) -> f32 {
+    self.seed ^= self.seed << 13;
+    self.seed ^= self.seed >> 17;
+    self.seed ^= self.seed << 5;
+
+    (self.seed as f32 / u32::MAX as f32) * 2.0 - 1.0
+  }
+
+  /// Paul Kellet's refined pink noise filter
+  fn pink(&mut self) -> f32 {
+    let white = self.white();
+    let b = &mut self.pink;
+
+    b[0] = 0.99886 * b[0] + white * 0.0555179;
+    b[1] = 0.99332 * b[1] + white * 0.0750759;
+    b[2] = 0.96900 * b[2] + white * 0.153852;
+    b[3] = 0.86650 * b[3] + white * 0.3104856;
+    b[4] = 0.55000 * b[4] + white * 0.5329522;
+    b[5] = -0.7616 * b[5] - white * 0.0168980;
+
+    let pink = b[0] + b[1] + b[2] + b[3] + b[4] + b[5] + b[6] + white * 0.5362;
+    b[6] = white * 0.115926;
+
+    pink * 0.11
+  }
+
+  fn advance(&mut self, frequency: f64) {
+    self.phase += frequency / self.settings.sample_rate as f64;
+
+    self.phase = self.phase.fract();
+  }
+}
+
+impl Iterator for Generator {
+  type Item = f32;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let value = match self.settings.signal {
+      Signal::Sine { frequency } => {
+        let value = (self.phase * TAU).sin() as f32;
+        self.advance(frequency as f64);
+        value
+      }
+      Signal::Square { frequency } => {
+        let value = if self.phase < 0.5 { 1.0 } else { -1.0 };
+        self.advance(frequency as f64);
+        value
+      }
+      Signal::Sweep { start, end, duration } => {
+        let (start, end, duration) = (start as f64, end as f64, duration as f64);
+        let time = (self.sample as f64 / self.settings.sample_rate as f64) % duration;
+        let frequency = start * (end / start).powf(time / duration);
+        let value = (self.phase * TAU).sin() as f32;
+
+        self.advance(frequency);
+        value
+      }
+      Signal::WhiteNoise => self.white(),
+      Signal::PinkNoise => self.pink(),
+      Signal::Impulse { frequency } => {
+        let period = (self.settings.sample_rate as f64 / frequency as f64).round().max(1.0) as u64;
+
+        match self.sample % period {
+          0 => 1.0,
+          _ => 0.0,
+        }
+      }
+    };
+
+    self.sample += 1;
+
+    Some(value * self.settings.amplitude)
+  }
+}
+
+/// Feeds a [`Generator`] to the sink in real time
+pub struct GeneratorSource {
+  settings: GeneratorSettings,
+  playing: Arc<AtomicBool>,
+  stopped: Arc<AtomicBool>,
+  thread: Option<JoinHandle<()>>,
+}
+
+impl GeneratorSource {
+  pub fn new(settings: GeneratorSettings) -> Self {
+    Self {
+      settings,
+      playing: Arc::default(),
+      stopped: Arc::default(),
+      thread: None,
+    }
+  }
+
+  pub fn settings(&self) -> &GeneratorSettings {
+    &self.settings
+  }
+}
+
+impl AudioSource for GeneratorSource {
+  fn name(&self) -> String {
+    format!("Generator ({})", self.settings.signal.name())
+  }
+
+  fn start(&mut self, mut sink: AudioSink) -> AudioResult<()> {
+    let mut generator = Generator::new(self.settings.clone());
+    let sample_rate = generator.settings().sample_rate;
+    let block = Duration::from_secs_f64(BLOCK_SIZE as f64 / sample_rate as f64);
+    let playing = self.playing.clone();
+    let stopped = self.stopped.clone();
+
+    sink.set_format(AudioFormat {
+      channels: 1,
+      sample_rate,
+    });
+
+    self.thread = Some(std::thread::spawn(move || {
+      let mut buffer = [0f32; BLOCK_SIZE];
+      let mut due = Instant::now();
+
+      while !stopped.load(Ordering::SeqCst) {
+        let now = Instant::now();
+
+        // Sleeps in steps so stopping doesn't wait for a whole block at low sample rates
+        if due > now {
+          std::thread::sleep((due - now).min(STOP_POLL));
+          continue;
+        }
+
+        if playing.load(Ordering::SeqCst) {
+          generator.fill(&mut buffer);
+          sink.push(&buffer);
+          due += block;
+        } else {
+          due = now + block;
+        }
+      }
+    }));
+
+    Ok(())
+  }
+
+  fn play(&mut self) -> AudioResult<()> {
+    self.playing.store(true, Ordering::SeqCst);
+    Ok(())
+  }
+
+  fn pause(&mut self) -> AudioResult<()> {
+    self.playing.store(false, Ordering::SeqCst);
+    Ok(())
+  }
+}
+
+impl Drop for GeneratorSource {
+  fn drop(&mut self) {
+    self.stopped.store(true, Ordering::SeqCst);
+
+    if let Some(thread) = self.thread.take() {
+      let _ = thread.join();
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::audio::{AudioData, AudioMode};
+  use crate::fft::{FFTSize, FftPlanner};
+  use crate::source::AudioFormat;
+  use crate::source::generator::{Generator, GeneratorSettings, GeneratorSource, Signal};
+  use crate::source::tests::audio;
+
+  fn generate(signal: Signal, sample_rate: u32, len: usize) -> Vec<f32> {
+    let mut settings = GeneratorSettings::new(signal);
+    settings.sample_rate = sample_rate;
+    settings.amplitude = 1.0;
+
+    Generator::new(settings).take(len).collect()
+  }
+
+  fn peak(data: &[f32]) -> usize {
+    (0..data.len()).max_by(|a, b| data[*a].total_cmp(&data[*b])).unwrap()
+  }
+
+  #[test]
+  fn sine_lands_in_its_bin() {
+    let format = AudioFormat {
+      channels: 1,
+      sample_rate: 1024,
+    };
+
+    let mut planner = FftPlanner::new();
+
+    // 1024 hz over 64 bins is 16 hz per bin
+    for frequency in (16..512).step_by(16) {
+      let samples = generate(Signal::Sine { frequency: frequency as f32 }, format.sample_rate, 64);
+      let data = AudioData::new(&samples, AudioMode::FFT(FFTSize::FFT64), format, &mut planner);
+      let bin = peak(&data[..32]);
+
+      assert_eq!(Some(bin), data.frequency_to_bin(frequency as f32));
+      assert_eq!(Some(frequency as f32), data.bin_frequency(bin));
+      // Its mirror in the upper half is the same frequency
+      assert_eq!(Some(frequency as f32), data.bin_frequency(64 - bin));
+      assert_eq!(Some(64 - bin), (32..64).find(|it| data[*it] == data[bin]));
+    }
+
+    let data = AudioData::new(&[], AudioMode::FFT(FFTSize::FFT64), format, &mut planner);
+    assert_eq!(Some(0.0), data.bin_frequency(0));
+    assert_eq!(Some(512.0), data.bin_frequency(32));
+    assert_eq!(Some(16.0), data.bin_frequency(63));
+    assert_eq!(None, data.bin_frequency(64));
+  }
+
+  #[test]
+  fn impulses_are_evenly_spaced() {
+    let samples = generate(Signal::Impulse { frequency: 100.0 }, 1000, 100);
+    let impulses = (0..samples.len()).filter(|it| samples[*it] == 1.0).collect::<Vec<_>>();
+
+    assert_eq!(impulses, vec![0, 10, 20, 30, 40, 50, 60, 70, 80, 90]);
+  }
+
+  #[test]
+  fn invalid_settings_are_clamped() {
+    let sweep = Signal::Sweep {
+      start: 0.0,
+      end: 20000.0,
+      duration: 0.0,
+    };
+
+    for (signal, sample_rate) in [(sweep, 48000), (Signal::Sine { frequency: 440.0 }, 0)] {
+      assert!(generate(signal, sample_rate, 4800).iter().all(|it| it.is_finite()));
+    }
+
+    let mut settings = GeneratorSettings::new(sweep);
+    settings.sample_rate = 0;
+
+    let mut audio = audio(AudioMode::Wave);
+
+    audio.change_source(GeneratorSource::new(settings)).unwrap();
+    assert_eq!(audio.source().unwrap().name(), "Generator (Sweep)");
+  }
+
+  #[test]
+  fn noise_stays_in_range() {
+    for signal in [Signal::WhiteNoise, Signal::PinkNoise] {
+      let samples = generate(signal, 48000, 48000);
+
+      assert!(samples.iter().all(|it| (-1.0..=1.0).contains(it)));
+      assert!(samples.iter().any(|it| *it != samples[0]));
+    }
+  }
+}
@@ -0,0 +1,326 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::error::AudioResult;
+use crate::source::{AudioFormat, AudioSink, AudioSource};
+
+/// How many frames are read from the pipe and pushed to the sink at once
+const CHUNK_FRAMES: usize = 512;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub enum PcmFormat {
+  S16LE,
+  F32LE,
+}
+
+impl PcmFormat {
+  pub const ALL: &'static [Self] = &[PcmFormat::S16LE, PcmFormat::F32LE];
+
+  pub const fn name(&self) -> &'static str {
+    match self {
+      PcmFormat::S16LE => "s16le",
+      PcmFormat::F32LE => "f32le",
+    }
+  }
+
+  pub const fn bytes(&self) -> usize {
+    match self {
+      PcmFormat::S16LE => 2,
+      PcmFormat::F32LE => 4,
+    }
+  }
+
+  fn decode(&self, bytes: &[u8], out: &mut Vec<f32>) {
+    out.clear();
+
+    match self {
+      PcmFormat::S16LE => out.extend(
+        bytes
+          .chunks_exact(2)
+          .map(|it| i16::from_le_bytes([it[0], it[1]]) as f32 / 32768.0),
+      ),
+      PcmFormat::F32LE => out.extend(
+        bytes
+          .chunks_exact(4)
+          .map(|it| f32::from_le_bytes([it[0], it[1], it[2], it[3]])),
+      ),
+    }
+  }
+}
+
+/// Interleaved raw PCM, like MPD's fifo output, `parec` or `ffmpeg -f f32le -`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PipeSettings {
+  /// Named pipe or file to read from, stdin is used when there isn't one
+  pub path: Option<PathBuf>,
+  pub format: PcmFormat,
+  pub sample_rate: u32,
+  pub channels: u16,
+}
+
+impl Default for PipeSettings {
+  /// Same as MPD's default fifo format, `44100:16:2`
+  fn default() -> Self {
+    Self {
+      path: None,
+      format: PcmFormat::S16LE,
+      sample_rate: 44100,
+      channels: 2,
+    }
+  }
+}
+
+/// Reads raw PCM from stdin or a named pipe
+pub struct PipeSource {
+  settings: PipeSettings,
+  playing: Arc<AtomicBool>,
+  stopped: Arc<AtomicBool>,
+}
+
+impl PipeSource {
+  pub fn new(settings: PipeSettings) -> Self {
+    Self {
+      settings,
+      playing: Arc::default(),
+      stopped: Arc::default(),
+    }
+  }
+
+  pub fn settings(&self) -> &PipeSettings {
+    &self.settings
+  }
+}
+
+impl AudioSource for PipeSource {
+  fn name(&self) -> String {
+    match &self.settings.path {
+      None => String::from("stdin"),
+      Some(path) => path.display().to_string(),
+    }
+  }
+
+  fn start(&mut self, mut sink: AudioSink) -> AudioResult<()> {
+    let settings = self.settings.clone();
+    let playing = self.playing.clone();
+    let stopped = self.stopped.clone();
+
+    // Opening a named pipe blocks until there's a writer, so only check that it's there
+    if let Some(path) = &settings.path {
+      std::fs::metadata(path)?;
+    }
+
+    sink.set_format(AudioFormat {
+      channels: self.settings.channels,
+      sample_rate: self.settings.sample_rate,
+    });
+
+    // Not joined when dropped, reads block until the writer sends something or closes the pipe,
+    // and opening a named pipe blocks until there's a writer, so the thread can outlive the source
+    // until then. It doesn't push anything once stopped, but it keeps holding stdin until its read returns.
+    std::thread::spawn(move || {
+      let result = match &settings.path {
+        None => read_pipe(std::io::stdin().lock(), &settings, &mut sink, &playing, &stopped),
+        Some(path) => read_path(path, &settings, &mut sink, &playing, &stopped),
+      };
+
+      if let Err(err) = result {
+        println!("{:?}", err);
+      }
+    });
+
+    Ok(())
+  }
+
+  fn play(&mut self) -> AudioResult<()> {
+    self.playing.store(true, Ordering::SeqCst);
+    Ok(())
+  }
+
+  fn pause(&mut self) -> AudioResult<()> {
+    self.playing.store(false, Ordering::SeqCst);
+    Ok(())
+  }
+}
+
+impl Drop for PipeSource {
+  fn drop(&mut self) {
+    self.stopped.store(true, Ordering::SeqCst);
+  }
+}
+
+/// Named pipes are opened again once the writer closes them, which MPD does every time it stops,
+/// regular files are only read once
+fn read_path(
+  path: &Path,
+  settings: &PipeSettings,
+  sink: &mut AudioSink,
+  playing: &AtomicBool,
+  stopped: &AtomicBool,
+) -> std::io::Result<()> {
+  loop {
+    // Blocks until there's a writer again
+    read_pipe(File::open(path)?, settings, sink, playing, stopped)?;
+
+    if stopped.load(Ordering::SeqCst) || std::fs::metadata(path)?.is_file() {
+      return Ok(());
+    }
+  }
+}
+
+/// Reads until the writer closes the pipe or the source is stopped
+fn read_pipe(
+  mut reader: impl Read,
+  settings: &PipeSettings,
+  sink: &mut AudioSink,
+  playing: &AtomicBool,
+  stopped: &AtomicBool,
+) -> std::io::Result<()> {
+  let frame = settings.format.bytes() * settings.channels.max(1) as usize;
+  let mut bytes = vec![0u8; CHUNK_FRAMES * frame];
+  let mut samples = Vec::with_capacity(CHUNK_FRAMES * settings.channels.max(1) as usize);
+
+  while !stopped.load(Ordering::SeqCst) {
+    let read = read_chunk(&mut reader, &mut bytes)?;
+    // A partial frame at the end can't be played
+    let chunk = &bytes[..read - read % frame];
+
+    // The read can block for a long time, the sink may be gone by now
+    if stopped.load(Ordering::SeqCst) {
+      return Ok(());
+    }
+
+    // Keep draining while paused so the writer doesn't block
+    if !chunk.is_empty() && playing.load(Ordering::SeqCst) {
+      settings.format.decode(chunk, &mut samples);
+      sink.push(&samples);
+    }
+
+    if read < bytes.len() {
+      return Ok(());
+    }
+  }
+
+  Ok(())
+}
+
+/// Fills `bytes` unless the writer closes the pipe first, returns how much was read
+fn read_chunk(reader: &mut impl Read, bytes: &mut [u8]) -> std::io::Result<usize> {
+  let mut read = 0;
+
+  while read < bytes.len() {
+    match reader.read(&mut bytes[read..]) {
+      Ok(0) => break,
+      Ok(count) => read += count,
+      Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+      Err(err) => return Err(err),
+    }
+  }
+
+  Ok(read)
+}
+
+#[cfg(test)]
+mod tests {
+  use std::io::{Cursor, Read};
+  use std::sync::atomic::{AtomicBool, Ordering};
+
+  use crate::audio::{AudioMode, ChannelMode};
+  use crate::broadcast::{Broadcast, Subscription};
+  use crate::sink::SinkCommand;
+  use crate::sink::tests::stereo_sink;
+  use crate::source::AudioFormat;
+  use crate::source::pipe::{CHUNK_FRAMES, PcmFormat, PipeSettings, read_pipe};
+
+  /// Stops the source while it's being read from, like dropping it during a blocking read would
+  struct StoppingReader<'a>(Cursor<Vec<u8>>, &'a AtomicBool);
+
+  impl Read for StoppingReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+      self.1.store(true, Ordering::SeqCst);
+      self.0.read(buf)
+    }
+  }
+
+  /// Reads all of `bytes` as mono s16le, returning what was pushed and how much was left unread
+  fn read(bytes: Vec<u8>, playing: bool) -> (Subscription, u64) {
+    let (mut sink, _, commands) = stereo_sink(AudioMode::Wave, ChannelMode::Mono);
+    let (subscription, subscriber) = Broadcast::new().subscribe();
+    let _ = commands.send(SinkCommand::Subscribe(subscriber));
+
+    let settings = PipeSettings {
+      channels: 1,
+      ..PipeSettings::default()
+    };
+
+    sink.set_format(AudioFormat {
+      channels: 1,
+      sample_rate: settings.sample_rate,
+    });
+
+    let len = bytes.len() as u64;
+    let mut reader = Cursor::new(bytes);
+
+    read_pipe(&mut reader, &settings, &mut sink, &AtomicBool::new(playing), &AtomicBool::new(false)).unwrap();
+    (subscription, len - reader.position())
+  }
+
+  /// `frames` of mono s16le, counting up, with a stray byte at the end
+  fn pcm(frames: usize) -> Vec<u8> {
+    let mut bytes: Vec<u8> = (0..frames).flat_map(|it| (it as i16).to_le_bytes()).collect();
+    bytes.push(0xFF);
+    bytes
+  }
+
+  #[test]
+  fn decodes_little_endian_samples() {
+    let mut out = Vec::new();
+
+    PcmFormat::S16LE.decode(&[0x00, 0x40, 0x00, 0xC0, 0xFF, 0x7F], &mut out);
+    assert_eq!(out, vec![0.5, -0.5, 32767.0 / 32768.0]);
+
+    PcmFormat::F32LE.decode(&[0.25f32.to_le_bytes(), (-1f32).to_le_bytes()].concat(), &mut out);
+    assert_eq!(out, vec![0.25, -1.0]);
+  }
+
+  #[test]
+  fn reads_until_the_writer_closes() {
+    let (subscription, unread) = read(pcm(CHUNK_FRAMES + 3), true);
+    let frames: Vec<usize> = subscription.try_iter().map(|it| it.data.len()).collect();
+
+    assert_eq!(unread, 0);
+    assert_eq!(frames, vec![CHUNK_FRAMES, 3]);
+  }
+
+  #[test]
+  fn decodes_what_it_reads() {
+    let (subscription, _) = read(pcm(4), true);
+
+    assert_eq!(subscription.recv().unwrap().data, vec![0.0, 1.0 / 32768.0, 2.0 / 32768.0, 3.0 / 32768.0]);
+  }
+
+  #[test]
+  fn drains_while_paused() {
+    let (subscription, unread) = read(pcm(CHUNK_FRAMES * 2), false);
+
+    assert_eq!(unread, 0);
+    assert!(subscription.try_recv().is_err());
+  }
+
+  #[test]
+  fn stopping_during_a_read_pushes_nothing() {
+    let (mut sink, _, commands) = stereo_sink(AudioMode::Wave, ChannelMode::Mono);
+    let (subscription, subscriber) = Broadcast::new().subscribe();
+    let _ = commands.send(SinkCommand::Subscribe(subscriber));
+    let stopped = AtomicBool::new(false);
+    let reader = StoppingReader(Cursor::new(pcm(CHUNK_FRAMES)), &stopped);
+
+    read_pipe(reader, &PipeSettings::default(), &mut sink, &AtomicBool::new(true), &stopped).unwrap();
+    assert!(subscription.try_recv().is_err());
+  }
+}
@@ -0,0 +1,107 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Analyzes FFT frames every `hop` samples instead of once per push, so subscribers get evenly spaced,
+/// overlapping frames even at big FFT sizes. Frames are still analyzed when the source pushes,
+/// so [`Audio::data`](crate::audio::Audio::data) only changes as often as the source's buffers arrive.
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StftSettings {
+  pub enabled: bool,
+  /// How much of a frame is shared with the one before it, 0.5 and 0.75 being common
+  pub overlap: f32,
+}
+
+impl StftSettings {
+  pub const MAX_OVERLAP: f32 = 0.95;
+
+  /// Samples between the start of two frames of `size` samples
+  pub fn hop(&self, size: usize) -> usize {
+    let overlap = self.overlap.clamp(0.0, Self::MAX_OVERLAP);
+
+    ((size as f32 * (1.0 - overlap)).round() as usize).max(1)
+  }
+}
+
+impl Default for StftSettings {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      overlap: 0.5,
+    }
+  }
+}
+
+/// Keeps track of where the next frame ends across pushes
+pub(crate) struct Stft {
+  settings: StftSettings,
+  /// Samples pushed after the end of the last frame
+  pending: usize,
+}
+
+impl Stft {
+  pub(crate) fn new(settings: StftSettings) -> Self {
+    Self { settings, pending: 0 }
+  }
+
+  pub(crate) fn settings(&self) -> StftSettings {
+    self.settings
+  }
+
+  pub(crate) fn set_settings(&mut self, settings: StftSettings) {
+    self.settings = settings;
+  }
+
+  /// Frames that would need samples older than `limit` are skipped
+  pub(crate) fn push(&mut self, samples: usize, limit: usize) {
+    self.pending = (self.pending + samples).min(limit);
+  }
+
+  /// How many samples before the newest one the next frame of `size` ends, `None` until enough were pushed
+  pub(crate) fn next_frame(&mut self, size: usize) -> Option<usize> {
+    let hop = self.settings.hop(size);
+
+    if self.pending < hop {
+      return None;
+    }
+
+    self.pending -= hop;
+    Some(self.pending)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::stft::{Stft, StftSettings};
+
+  #[test]
+  fn frames_follow_the_hop() {
+    let mut stft = Stft::new(StftSettings {
+      enabled: true,
+      overlap: 0.75,
+    });
+
+    assert_eq!(stft.settings().hop(1024), 256);
+
+    stft.push(600, usize::MAX);
+    assert_eq!(stft.next_frame(1024), Some(344));
+    assert_eq!(stft.next_frame(1024), Some(88));
+    assert_eq!(stft.next_frame(1024), None);
+
+    // The rest carries over to the next push
+    stft.push(200, usize::MAX);
+    assert_eq!(stft.next_frame(1024), Some(32));
+    assert_eq!(stft.next_frame(1024), None);
+  }
+
+  #[test]
+  fn hop_is_never_zero() {
+    let settings = StftSettings {
+      enabled: true,
+      overlap: 1.0,
+    };
+
+    assert_eq!(settings.hop(16), 1);
+    assert_eq!(settings.hop(1024), 51);
+  }
+}
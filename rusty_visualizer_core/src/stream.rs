@@ -0,0 +1,144 @@
+use cpal::{BufferSize, Device, SampleRate, SupportedBufferSize, SupportedStreamConfig, SupportedStreamConfigRange};
+use cpal::traits::DeviceTrait;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::error::AudioResult;
+
+/// Overrides for how device streams are opened, anything that isn't set uses the device's default.
+///
+/// Smaller buffers mean less latency but more callbacks, so more CPU.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StreamSettings {
+  pub sample_rate: Option<u32>,
+  /// In frames, clamped to what the device supports
+  pub buffer_size: Option<u32>,
+  pub channels: Option<u16>,
+}
+
+impl StreamSettings {
+  /// Finds a supported config that's as close to these settings as possible.
+  ///
+  /// When the channel count and sample rate aren't supported together, the channel count
+  /// falls back to the default first, then the sample rate, then both.
+  pub(crate) fn negotiate(&self, device: &Device, input: bool) -> AudioResult<SupportedStreamConfig> {
+    let default = if input {
+      device.default_input_config()?
+    } else {
+      device.default_output_config()?
+    };
+
+    if self.channels.is_none() && self.sample_rate.is_none() {
+      return Ok(default);
+    }
+
+    let supported: Vec<SupportedStreamConfigRange> = match input {
+      true => device.supported_input_configs().map(Iterator::collect),
+      false => device.supported_output_configs().map(Iterator::collect),
+    }
+    .unwrap_or_default();
+
+    let config = self
+      .candidates(default.channels(), default.sample_rate().0)
+      .into_iter()
+      .find_map(|(channels, sample_rate)| find_config(&supported, &default, channels, sample_rate))
+      .unwrap_or_else(|| default.clone());
+
+    if self.channels.unwrap_or(config.channels()) != config.channels()
+      || self.sample_rate.unwrap_or(config.sample_rate().0) != config.sample_rate().0
+    {
+      println!(
+        "{:?} channels at {:?} Hz isn't supported, using {} channels at {} Hz",
+        self.channels,
+        self.sample_rate,
+        config.channels(),
+        config.sample_rate().0,
+      );
+    }
+
+    Ok(config)
+  }
+
+  /// Channel counts and sample rates to try, in order, before giving up and using the default
+  fn candidates(&self, channels: u16, sample_rate: u32) -> Vec<(u16, u32)> {
+    let wanted = (self.channels.unwrap_or(channels), self.sample_rate.unwrap_or(sample_rate));
+    let mut candidates = Vec::with_capacity(3);
+
+    for candidate in [wanted, (channels, wanted.1), (wanted.0, sample_rate)] {
+      if candidate != (channels, sample_rate) && !candidates.contains(&candidate) {
+        candidates.push(candidate);
+      }
+    }
+
+    candidates
+  }
+
+  pub(crate) fn buffer_size(&self, supported: &SupportedBufferSize) -> BufferSize {
+    match (self.buffer_size, supported) {
+      (None, _) => BufferSize::Default,
+      (Some(size), SupportedBufferSize::Range { min, max }) => BufferSize::Fixed(size.clamp(*min, *max)),
+      // Nothing to clamp to, so let the device decide if it works
+      (Some(size), SupportedBufferSize::Unknown) => BufferSize::Fixed(size),
+    }
+  }
+}
+
+/// Prefers the default's sample format if there's more than one
+fn find_config(
+  supported: &[SupportedStreamConfigRange],
+  default: &SupportedStreamConfig,
+  channels: u16,
+  sample_rate: u32,
+) -> Option<SupportedStreamConfig> {
+  let mut matching = supported.iter().filter(|it| {
+    it.channels() == channels && it.min_sample_rate().0 <= sample_rate && sample_rate <= it.max_sample_rate().0
+  });
+
+  let range = matching
+    .clone()
+    .find(|it| it.sample_format() == default.sample_format())
+    .or_else(|| matching.next())?;
+
+  Some(range.clone().with_sample_rate(SampleRate(sample_rate)))
+}
+
+#[cfg(test)]
+mod tests {
+  use cpal::{BufferSize, SupportedBufferSize};
+
+  use crate::stream::StreamSettings;
+
+  #[test]
+  fn falls_back_one_setting_at_a_time() {
+    let settings = StreamSettings {
+      sample_rate: Some(96000),
+      channels: Some(4),
+      ..StreamSettings::default()
+    };
+
+    assert_eq!(settings.candidates(2, 48000), vec![(4, 96000), (2, 96000), (4, 48000)]);
+    assert_eq!(StreamSettings::default().candidates(2, 48000), vec![]);
+
+    let settings = StreamSettings {
+      sample_rate: Some(96000),
+      ..StreamSettings::default()
+    };
+
+    assert_eq!(settings.candidates(2, 48000), vec![(2, 96000)]);
+  }
+
+  #[test]
+  fn clamps_buffer_size() {
+    let supported = SupportedBufferSize::Range { min: 64, max: 4096 };
+    let buffer_size = |size| StreamSettings {
+      buffer_size: size,
+      ..StreamSettings::default()
+    };
+
+    assert_eq!(buffer_size(None).buffer_size(&supported), BufferSize::Default);
+    assert_eq!(buffer_size(Some(16)).buffer_size(&supported), BufferSize::Fixed(64));
+    assert_eq!(buffer_size(Some(256)).buffer_size(&supported), BufferSize::Fixed(256));
+    assert_eq!(buffer_size(Some(256)).buffer_size(&SupportedBufferSize::Unknown), BufferSize::Fixed(256));
+  }
+}
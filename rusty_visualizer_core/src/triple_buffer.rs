@@ -0,0 +1,125 @@
+use std::cell::UnsafeCell;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Set on the back buffer index when it holds something the output hasn't seen yet
+const DIRTY: u8 = 0b100;
+const INDEX: u8 = 0b011;
+
+/// Three preallocated buffers, one for each side and one in between that gets swapped with either,
+/// neither side ever waits on the other or allocates.
+struct Shared<T> {
+  buffers: [UnsafeCell<T>; 3],
+  back: AtomicU8,
+}
+
+// Each buffer is only ever accessed by the side that currently owns its index
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+pub struct TripleBufferInput<T> {
+  shared: Arc<Shared<T>>,
+  index: usize,
+}
+
+pub struct TripleBufferOutput<T> {
+  shared: Arc<Shared<T>>,
+  index: usize,
+}
+
+/// Creates every buffer with `new`, rather than cloning, so they keep any preallocated capacity
+pub fn triple_buffer<T>(new: impl Fn() -> T) -> (TripleBufferInput<T>, TripleBufferOutput<T>) {
+  let shared = Arc::new(Shared {
+    buffers: [UnsafeCell::new(new()), UnsafeCell::new(new()), UnsafeCell::new(new())],
+    back: AtomicU8::new(1),
+  });
+
+  let input = TripleBufferInput {
+    shared: shared.clone(),
+    index: 0,
+  };
+
+  let output = TripleBufferOutput { shared, index: 2 };
+
+  (input, output)
+}
+
+impl<T> TripleBufferInput<T> {
+  /// Buffer to write the next value into, it still holds whatever was there before
+  pub fn input_buffer(&mut self) -> &mut T {
+    unsafe { &mut *self.shared.buffers[self.index].get() }
+  }
+
+  /// Makes the input buffer visible to the output
+  pub fn publish(&mut self) {
+    let back = self.shared.back.swap(self.index as u8 | DIRTY, Ordering::AcqRel);
+    self.index = (back & INDEX) as usize;
+  }
+}
+
+impl<T> TripleBufferOutput<T> {
+  /// Takes the latest published value if there is one, returns whether it changed
+  pub fn update(&mut self) -> bool {
+    if self.shared.back.load(Ordering::Relaxed) & DIRTY == 0 {
+      return false;
+    }
+
+    let back = self.shared.back.swap(self.index as u8, Ordering::AcqRel);
+    self.index = (back & INDEX) as usize;
+    true
+  }
+
+  /// Latest value as of the last [`update`](Self::update)
+  pub fn peek(&self) -> &T {
+    unsafe { &*self.shared.buffers[self.index].get() }
+  }
+
+  pub fn read(&mut self) -> &T {
+    self.update();
+    self.peek()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::triple_buffer::triple_buffer;
+
+  #[test]
+  fn reads_latest_value() {
+    let (mut input, mut output) = triple_buffer(|| 0);
+
+    assert_eq!(*output.read(), 0);
+    assert!(!output.update());
+
+    *input.input_buffer() = 1;
+    input.publish();
+    *input.input_buffer() = 2;
+    input.publish();
+
+    assert_eq!(*output.read(), 2);
+    assert!(!output.update());
+    assert_eq!(*output.peek(), 2);
+  }
+
+  #[test]
+  fn never_tears_across_threads() {
+    let (mut input, mut output) = triple_buffer(|| vec![0u32; 64]);
+
+    let writer = std::thread::spawn(move || {
+      for i in 1..=10000 {
+        input.input_buffer().iter_mut().for_each(|it| *it = i);
+        input.publish();
+      }
+    });
+
+    let mut last = 0;
+
+    while last < 10000 {
+      let value = output.read();
+      assert!(value.iter().all(|it| *it == value[0]));
+      assert!(value[0] >= last);
+      last = value[0];
+    }
+
+    writer.join().unwrap();
+  }
+}
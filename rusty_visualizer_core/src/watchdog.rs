@@ -0,0 +1,288 @@
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use serde::Serialize;
+
+/// When and how often a source that errored or went quiet gets reopened, times are in seconds
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatchdogSettings {
+  pub enabled: bool,
+  /// How long a live source can go without sending audio before it's considered gone, off by default
+  /// since loopback and some input devices stop calling back whenever nothing is playing
+  pub timeout: Option<f32>,
+  /// Wait before the first attempt, doubled after every failed one
+  pub min_backoff: f32,
+  pub max_backoff: f32,
+}
+
+impl Default for WatchdogSettings {
+  fn default() -> Self {
+    Self {
+      enabled: true,
+      timeout: None,
+      min_backoff: 0.5,
+      max_backoff: 30.0,
+    }
+  }
+}
+
+impl WatchdogSettings {
+  fn backoff(&self, attempt: u32) -> Duration {
+    let backoff = self.min_backoff * 2f32.powi(attempt.min(16) as i32);
+
+    Duration::from_secs_f32(backoff.min(self.max_backoff).max(0.0))
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SourceStatus {
+  /// There isn't a source
+  None,
+  Running,
+  /// The source errored or went quiet and is going to be reopened
+  Recovering { attempt: u32, reason: String },
+}
+
+impl Display for SourceStatus {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      SourceStatus::None => write!(f, "No source"),
+      SourceStatus::Running => write!(f, "Running"),
+      SourceStatus::Recovering { attempt, reason } => write!(f, "Reconnecting (attempt {}): {}", attempt + 1, reason),
+    }
+  }
+}
+
+/// Shared between a source and the [`Audio`](crate::audio::Audio) watching it,
+/// the sink beats on every push and sources report their stream errors here.
+pub struct Heartbeat {
+  start: Instant,
+  /// Milliseconds since `start` of the last beat
+  last: AtomicU64,
+  alive: AtomicBool,
+  /// Set once the source is being removed
+  closed: AtomicBool,
+  error: Mutex<Option<String>>,
+}
+
+impl Heartbeat {
+  pub(crate) fn new() -> Self {
+    Self {
+      start: Instant::now(),
+      last: AtomicU64::new(0),
+      alive: AtomicBool::new(false),
+      closed: AtomicBool::new(false),
+      error: Mutex::new(None),
+    }
+  }
+
+  pub(crate) fn beat(&self) {
+    self.last.store(self.start.elapsed().as_millis() as u64, Ordering::Relaxed);
+    self.alive.store(true, Ordering::Relaxed);
+  }
+
+  /// Reports an error that stopped the source, it gets reopened if the watchdog is enabled
+  pub fn fail(&self, err: impl Display) {
+    println!("{}", err);
+
+    if let Ok(mut error) = self.error.lock() {
+      error.get_or_insert_with(|| err.to_string());
+    }
+  }
+
+  /// Tells the sink to stop waiting on anything, the source is about to be dropped
+  pub(crate) fn close(&self) {
+    self.closed.store(true, Ordering::SeqCst);
+  }
+
+  pub(crate) fn is_closed(&self) -> bool {
+    self.closed.load(Ordering::SeqCst)
+  }
+
+  /// Time since the last beat, or since it was created if there hasn't been one
+  pub(crate) fn silence(&self) -> Duration {
+    self.start.elapsed().saturating_sub(Duration::from_millis(self.last.load(Ordering::Relaxed)))
+  }
+
+  fn take_error(&self) -> Option<String> {
+    self.error.lock().ok().and_then(|mut it| it.take())
+  }
+}
+
+/// Keeps an eye on the current source, telling [`Audio`](crate::audio::Audio) when to reopen it
+pub(crate) struct Watchdog {
+  heartbeat: Option<Arc<Heartbeat>>,
+  live: bool,
+  status: SourceStatus,
+  attempt: u32,
+  retry_at: Option<Instant>,
+}
+
+impl Watchdog {
+  pub(crate) fn new() -> Self {
+    Self {
+      heartbeat: None,
+      live: false,
+      status: SourceStatus::None,
+      attempt: 0,
+      retry_at: None,
+    }
+  }
+
+  pub(crate) fn status(&self) -> &SourceStatus {
+    &self.status
+  }
+
+  /// Watches a newly started source, `live` sources are also expected to keep sending audio
+  pub(crate) fn start(&mut self, heartbeat: Arc<Heartbeat>, live: bool) {
+    self.heartbeat = Some(heartbeat);
+    self.live = live;
+    self.status = SourceStatus::Running;
+    self.retry_at = None;
+  }
+
+  pub(crate) fn stop(&mut self) {
+    self.heartbeat = None;
+    self.status = SourceStatus::None;
+    self.attempt = 0;
+    self.retry_at = None;
+  }
+
+  /// Returns the attempt to make if the source should be reopened now
+  pub(crate) fn check(&mut self, settings: &WatchdogSettings, playing: bool) -> Option<u32> {
+    if !settings.enabled {
+      return None;
+    }
+
+    let timeout = settings.timeout.filter(|_| self.live && playing);
+
+    match self.status {
+      SourceStatus::None => None,
+      SourceStatus::Recovering { .. } => match self.retry_at {
+        Some(retry_at) if Instant::now() < retry_at => None,
+        _ => Some(self.attempt),
+      },
+      SourceStatus::Running => {
+        let heartbeat = self.heartbeat.as_ref()?;
+
+        match heartbeat.take_error() {
+          Some(err) => self.fail(err, settings),
+          None if timeout.is_some_and(|it| heartbeat.silence().as_secs_f32() > it) => {
+            self.fail(format!("No audio for {} seconds", timeout.unwrap_or_default()), settings)
+          }
+          // Only start over once it actually works again
+          None if !self.live || heartbeat.alive.load(Ordering::Relaxed) => self.attempt = 0,
+          None => {}
+        }
+
+        None
+      }
+    }
+  }
+
+  /// Called after reopening for `attempt`, before [`fail`](Self::fail) if it didn't work
+  pub(crate) fn retried(&mut self, attempt: u32) {
+    self.attempt = attempt + 1;
+  }
+
+  /// Schedules the next attempt
+  pub(crate) fn fail(&mut self, reason: String, settings: &WatchdogSettings) {
+    self.heartbeat = None;
+    self.retry_at = Some(Instant::now() + settings.backoff(self.attempt));
+    self.status = SourceStatus::Recovering {
+      attempt: self.attempt,
+      reason,
+    };
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::Arc;
+  use std::time::Duration;
+
+  use crate::watchdog::{Heartbeat, SourceStatus, Watchdog, WatchdogSettings};
+
+  #[test]
+  fn backoff_doubles_up_to_max() {
+    let settings = WatchdogSettings {
+      min_backoff: 0.5,
+      max_backoff: 3.0,
+      ..WatchdogSettings::default()
+    };
+
+    assert_eq!(settings.backoff(0), Duration::from_secs_f32(0.5));
+    assert_eq!(settings.backoff(1), Duration::from_secs_f32(1.0));
+    assert_eq!(settings.backoff(2), Duration::from_secs_f32(2.0));
+    assert_eq!(settings.backoff(3), Duration::from_secs_f32(3.0));
+    assert_eq!(settings.backoff(100), Duration::from_secs_f32(3.0));
+  }
+
+  #[test]
+  fn errors_are_retried() {
+    let settings = WatchdogSettings {
+      min_backoff: 0.0,
+      ..WatchdogSettings::default()
+    };
+    let heartbeat = Arc::new(Heartbeat::new());
+    let mut watchdog = Watchdog::new();
+
+    watchdog.start(heartbeat.clone(), true);
+    heartbeat.beat();
+    assert_eq!(watchdog.check(&settings, true), None);
+    assert_eq!(*watchdog.status(), SourceStatus::Running);
+
+    heartbeat.fail("gone");
+    assert_eq!(watchdog.check(&settings, true), None);
+    assert!(matches!(watchdog.status(), SourceStatus::Recovering { attempt: 0, .. }));
+    assert_eq!(watchdog.check(&settings, true), Some(0));
+
+    watchdog.retried(0);
+    watchdog.fail(String::from("still gone"), &settings);
+    assert_eq!(watchdog.check(&settings, true), Some(1));
+  }
+
+  #[test]
+  fn silent_live_sources_are_retried() {
+    let settings = WatchdogSettings {
+      timeout: Some(0.0),
+      ..WatchdogSettings::default()
+    };
+    let mut watchdog = Watchdog::new();
+
+    watchdog.start(Arc::new(Heartbeat::new()), false);
+    std::thread::sleep(Duration::from_millis(5));
+    watchdog.check(&settings, true);
+    assert_eq!(*watchdog.status(), SourceStatus::Running);
+
+    watchdog.start(Arc::new(Heartbeat::new()), true);
+    std::thread::sleep(Duration::from_millis(5));
+    watchdog.check(&settings, false);
+    assert_eq!(*watchdog.status(), SourceStatus::Running);
+
+    watchdog.check(&settings, true);
+    assert!(matches!(watchdog.status(), SourceStatus::Recovering { .. }));
+  }
+
+  #[test]
+  fn quiet_loopback_keeps_running() {
+    let settings = WatchdogSettings::default();
+    let heartbeat = Arc::new(Heartbeat::new());
+    let mut watchdog = Watchdog::new();
+
+    // Loopback doesn't call back while nothing is playing, so it never beats
+    watchdog.start(heartbeat.clone(), true);
+    std::thread::sleep(Duration::from_millis(5));
+    assert_eq!(watchdog.check(&settings, true), None);
+    assert_eq!(*watchdog.status(), SourceStatus::Running);
+
+    // Errors still get it reopened
+    heartbeat.fail("gone");
+    watchdog.check(&settings, true);
+    assert!(matches!(watchdog.status(), SourceStatus::Recovering { .. }));
+  }
+}
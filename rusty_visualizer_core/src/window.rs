@@ -0,0 +1,181 @@
+use std::f32::consts::PI;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Shapes the samples before the FFT, so a frequency between two bins doesn't leak into every other bin.
+///
+/// Narrower peaks come with more leakage, [`Rectangular`](Self::Rectangular) being the extreme
+/// and the same as not using a window.
+#[derive(Copy, Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum WindowFunction {
+  #[default]
+  Rectangular,
+  Hann,
+  Hamming,
+  BlackmanHarris,
+  /// Widest peaks, but their height is the most accurate
+  FlatTop,
+  /// Higher `beta` means less leakage but wider peaks, 0 is rectangular
+  Kaiser { beta: f32 },
+}
+
+impl WindowFunction {
+  pub const ALL: &'static [Self] = &[
+    WindowFunction::Rectangular,
+    WindowFunction::Hann,
+    WindowFunction::Hamming,
+    WindowFunction::BlackmanHarris,
+    WindowFunction::FlatTop,
+    WindowFunction::Kaiser { beta: 8.6 },
+  ];
+
+  pub const fn name(&self) -> &'static str {
+    match self {
+      WindowFunction::Rectangular => "Rectangular",
+      WindowFunction::Hann => "Hann",
+      WindowFunction::Hamming => "Hamming",
+      WindowFunction::BlackmanHarris => "Blackman-Harris",
+      WindowFunction::FlatTop => "Flat-top",
+      WindowFunction::Kaiser { .. } => "Kaiser",
+    }
+  }
+
+  /// Whether both are the same function, ignoring parameters
+  pub fn is_same_kind(&self, other: &Self) -> bool {
+    std::mem::discriminant(self) == std::mem::discriminant(other)
+  }
+
+  /// Value of the window at `index` out of `size`
+  pub fn coefficient(&self, index: usize, size: usize) -> f32 {
+    if size < 2 {
+      return 1.0;
+    }
+
+    let x = index as f32 / (size - 1) as f32;
+    let cosines = |a: &[f32]| {
+      a.iter()
+        .enumerate()
+        .map(|(k, a)| {
+          let sign = if k % 2 == 0 { 1.0 } else { -1.0 };
+          sign * a * (2.0 * PI * k as f32 * x).cos()
+        })
+        .sum()
+    };
+
+    match self {
+      WindowFunction::Rectangular => 1.0,
+      WindowFunction::Hann => cosines(&[0.5, 0.5]),
+      WindowFunction::Hamming => cosines(&[0.54, 0.46]),
+      WindowFunction::BlackmanHarris => cosines(&[0.35875, 0.48829, 0.14128, 0.01168]),
+      WindowFunction::FlatTop => cosines(&[0.21557895, 0.41663158, 0.27726316, 0.08357895, 0.006947368]),
+      WindowFunction::Kaiser { beta } => {
+        let r = 2.0 * x - 1.0;
+        bessel_i0(beta * (1.0 - r * r).max(0.0).sqrt()) / bessel_i0(*beta)
+      }
+    }
+  }
+}
+
+/// Zeroth order modified Bessel function of the first kind, only used by the Kaiser window
+fn bessel_i0(x: f32) -> f32 {
+  let mut sum = 1.0;
+  let mut term = 1.0;
+  let half = x / 2.0;
+
+  for k in 1..50 {
+    term *= half / k as f32;
+    let squared = term * term;
+    sum += squared;
+
+    if squared < sum * 1e-8 {
+      break;
+    }
+  }
+
+  sum
+}
+
+/// Coefficients of a [`WindowFunction`] for one size, only recomputed when either changes
+pub(crate) struct Window {
+  function: WindowFunction,
+  coefficients: Vec<f32>,
+}
+
+impl Window {
+  pub(crate) fn new(function: WindowFunction) -> Self {
+    Self {
+      function,
+      coefficients: Vec::new(),
+    }
+  }
+
+  pub(crate) fn set_function(&mut self, function: WindowFunction) {
+    if self.function != function {
+      self.function = function;
+      self.coefficients.clear();
+    }
+  }
+
+  /// Multiplies `samples` by the window, scaled so it doesn't change the level of a steady tone
+  pub(crate) fn apply(&mut self, samples: &mut [f32]) {
+    if self.function == WindowFunction::Rectangular {
+      return;
+    }
+
+    let size = samples.len();
+
+    if self.coefficients.len() != size {
+      let function = self.function;
+
+      self.coefficients.clear();
+      self.coefficients.extend((0..size).map(|it| function.coefficient(it, size)));
+
+      let mean = self.coefficients.iter().sum::<f32>() / size.max(1) as f32;
+
+      for coefficient in &mut self.coefficients {
+        *coefficient /= mean;
+      }
+    }
+
+    for (sample, coefficient) in samples.iter_mut().zip(&self.coefficients) {
+      *sample *= coefficient;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::window::{Window, WindowFunction};
+
+  #[test]
+  fn windows_are_symmetric_and_peak_in_the_middle() {
+    for function in WindowFunction::ALL {
+      let size = 33;
+      let middle = function.coefficient(size / 2, size);
+
+      for index in 0..size {
+        let coefficient = function.coefficient(index, size);
+
+        assert!((coefficient - function.coefficient(size - 1 - index, size)).abs() < 1e-5, "{:?}", function);
+        assert!(coefficient <= middle + 1e-5, "{:?}", function);
+      }
+
+      assert!((middle - 1.0).abs() < 1e-3, "{:?} {}", function, middle);
+    }
+
+    assert!(WindowFunction::Hann.coefficient(0, 33).abs() < 1e-6);
+  }
+
+  #[test]
+  fn keeps_the_level() {
+    for function in WindowFunction::ALL {
+      let mut window = Window::new(*function);
+      let mut samples = [1.0; 64];
+      window.apply(&mut samples);
+
+      let mean = samples.iter().sum::<f32>() / samples.len() as f32;
+      assert!((mean - 1.0).abs() < 1e-4, "{:?} {}", function, mean);
+    }
+  }
+}
@@ -13,7 +13,7 @@ pub trait Application {
 
   fn ui(&mut self, _ctx: &egui::CtxRef) {}
 
-  fn before_draw(&mut self) {}
+  fn before_draw(&mut self, _ctx: &egui::CtxRef) {}
 
   fn draw(&self, ctx: &egui::CtxRef) {}
 
@@ -28,7 +28,7 @@ pub async fn run_application<App: Application>() {
 
   loop {
     egui_macroquad::ui(|ctx| {
-      app.before_draw();
+      app.before_draw(ctx);
       app.draw(ctx);
       app.after_draw();
 
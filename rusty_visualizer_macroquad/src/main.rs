@@ -2,8 +2,10 @@
 
 use std::borrow::Cow;
 use std::f32::consts::TAU;
+use std::ops::RangeInclusive;
 use std::sync::{Arc, RwLock, RwLockReadGuard};
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use egui::{Align, CtxRef, Order};
 use image::RgbaImage;
@@ -12,9 +14,18 @@ use macroquad_text::Fonts;
 use serde::{Deserialize, Serialize};
 use spotify_info::{SpotifyEvent, SpotifyListener, TrackInfo, TrackState};
 
-use rusty_visualizer_core::audio::{Audio, AudioDevice, AudioMode, ToSerializableAudioDevice};
-use rusty_visualizer_core::cpal::traits::{DeviceTrait, HostTrait};
+use rusty_visualizer_core::audio::{Audio, AudioData, AudioDevice, AudioMode, ChannelMode, PlaybackState, ToSerializableAudioDevice};
+use rusty_visualizer_core::bands::BandAggregation;
+use rusty_visualizer_core::devices::{DeviceDirection, DeviceInfo};
+use rusty_visualizer_core::error::AudioResult;
+use rusty_visualizer_core::event::AudioEvent;
+use rusty_visualizer_core::scale::MagnitudeScale;
 use rusty_visualizer_core::settings::{AudioManager, AudioSettings, SettingsManager};
+use rusty_visualizer_core::source::{FileSettings, GeneratorSettings, PcmFormat, PipeSettings, Signal};
+use rusty_visualizer_core::stft::StftSettings;
+use rusty_visualizer_core::stream::StreamSettings;
+use rusty_visualizer_core::watchdog::SourceStatus;
+use rusty_visualizer_core::window::WindowFunction;
 
 use crate::application::{Application, run_application};
 use crate::cache::{ImageCache, ImageCacheType};
@@ -26,7 +37,9 @@ mod cache;
 mod color;
 mod util;
 
-const AUDIO_DEVICE_SWITCH_NOT_SUPPORT: &str = "Not supported on linux because ALSA is terrible, you can use something like pavucontrol instead";
+/// Seconds it takes to fade out when the audio goes silent, and back in when it comes back
+const FADE_TIME: f32 = 1f32;
+const AUDIO_DEVICE_SWITCH_NOT_SUPPORT: &str = "Not supported on linux because ALSA is terrible, you can use something like pavucontrol or the Pipe device type instead";
 
 pub const NOTO_SANS: &[u8] = include_bytes!("../../assets/NotoSans-Regular.ttf");
 pub const NOTO_SANS_JP: &[u8] = include_bytes!("../../assets/NotoSansJP-Regular.otf");
@@ -65,7 +78,7 @@ impl AudioManager for App {
     &mut self.audio
   }
 
-  fn change_device(&mut self, new_device: impl ToSerializableAudioDevice) {
+  fn change_device(&mut self, new_device: impl ToSerializableAudioDevice) -> AudioResult<()> {
     let new_device = new_device.to_serializable(self.audio());
 
     match &new_device {
@@ -80,14 +93,64 @@ impl AudioManager for App {
         self.settings.state.audio.device_type = AudioDeviceType::Output;
         self.settings.state.audio.output_device = Some(name.clone());
       }
+      AudioDevice::File(file) => {
+        self.settings.state.audio.device_type = AudioDeviceType::File;
+        self.settings.state.audio.file_path = file.path.display().to_string();
+      }
+      AudioDevice::Generator(generator) => {
+        self.settings.state.audio.device_type = AudioDeviceType::Generator;
+        self.settings.state.audio.generator = generator.clone();
+      }
+      AudioDevice::Pipe(pipe) => {
+        self.settings.state.audio.device_type = AudioDeviceType::Pipe;
+        self.settings.state.audio.pipe_path = pipe.path.as_ref().map(|it| it.display().to_string()).unwrap_or_default();
+        self.settings.state.audio.pipe = pipe.clone();
+      }
     }
 
-    self.audio_settings().device = new_device.clone();
-    self.audio().change_device(new_device);
+    self.audio_settings().device = new_device;
+
+    let settings = self.audio_settings().clone();
+    self.audio().open_device(&settings)
   }
 }
 //endregion
 
+/// Slider that's only there when the value is set, otherwise the default gets used
+fn optional_slider<T: egui::emath::Numeric>(
+  ui: &mut egui::Ui,
+  value: &mut Option<T>,
+  range: RangeInclusive<T>,
+  initial: T,
+  text: &str,
+) {
+  let mut enabled = value.is_some();
+
+  if ui.checkbox(&mut enabled, text).changed() {
+    *value = if enabled { Some(initial) } else { None };
+  }
+
+  if let Some(value) = value {
+    ui.add(egui::Slider::new(value, range));
+  }
+}
+
+fn describe_device(device: &DeviceInfo) -> String {
+  let sample_rates: Vec<String> = device
+    .sample_rates
+    .iter()
+    .map(|it| if it.start() == it.end() { it.start().to_string() } else { format!("{}-{}", it.start(), it.end()) })
+    .collect();
+
+  format!(
+    "{}\nChannels: {:?}\nSample Rates: {} Hz\nFormats: {:?}",
+    device.host,
+    device.channels,
+    sample_rates.join(", "),
+    device.sample_formats,
+  )
+}
+
 //region State
 #[derive(PartialEq, Debug, Clone)]
 enum AudioDeviceType {
@@ -96,6 +159,9 @@ enum AudioDeviceType {
   Loopback,
   Input,
   Output,
+  File,
+  Generator,
+  Pipe,
 }
 
 #[derive(Clone)]
@@ -104,6 +170,20 @@ struct AudioState {
   device_type: AudioDeviceType,
   input_device: Option<String>,
   output_device: Option<String>,
+  file_path: String,
+  generator: GeneratorSettings,
+  pipe: PipeSettings,
+  pipe_path: String,
+  /// Why the last device change failed
+  error: Option<String>,
+  /// Applied to the settings once it's done being edited
+  stream: StreamSettings,
+  silent: bool,
+  /// How visible everything is, goes to 0 while it's silent
+  fade: f32,
+  /// Listed when the device list is first opened, and again when it's refreshed
+  #[cfg(not(target_os = "linux"))]
+  devices: Option<Result<Vec<DeviceInfo>, String>>,
 }
 
 impl Default for AudioState {
@@ -113,6 +193,16 @@ impl Default for AudioState {
       device_type: AudioDeviceType::Default,
       input_device: None,
       output_device: None,
+      file_path: String::new(),
+      generator: GeneratorSettings::new(Signal::DEFAULTS[0]),
+      pipe: PipeSettings::default(),
+      pipe_path: String::new(),
+      error: None,
+      stream: StreamSettings::default(),
+      silent: false,
+      fade: 1f32,
+      #[cfg(not(target_os = "linux"))]
+      devices: None,
     }
   }
 }
@@ -243,6 +333,131 @@ impl App {
     }
   }
 
+  /// Log bands when there are any, so the bass isn't squeezed into a sliver
+  fn values(audio: &AudioData) -> &[f32] {
+    if audio.bands.is_empty() { &audio.data } else { &audio.bands }
+  }
+
+  fn draw_radial(&self, data: &[f32], sum: f32, start: f32, sweep: f32) {
+    let state = &self.settings.state.visualizer;
+    let len = data.len();
+    let center_w = state.offset_x + screen_width() / 2f32;
+    let center_h = state.offset_y + screen_height() / 2f32;
+
+    for i in 0..len {
+      let value = data[i] * 300f32 * state.size;
+      let mut color = self.settings.state.fg_color.as_color();
+      let gap = screen_width() / len as f32;
+
+      color.r = clamp(color.r * data[i] * 5f32, 0.2, 1.0);
+      color.g = clamp(color.g * data[i] * 5f32, 0.2, 1.0);
+      color.b = clamp(color.b * data[i] * 5f32, 0.2, 1.0);
+      color.a = self.settings.state.audio.fade;
+
+      let theta = start + (sweep / len as f32) * i as f32;
+      let radius = state.radius * sum / 200f32;
+
+      let x_inner = center_w + (radius - value) * theta.sin();
+      let y_inner = center_h - (radius - value) * theta.cos();
+      let x_outer = center_w + (radius + value) * theta.sin();
+      let y_outer = center_h - (radius + value) * theta.cos();
+
+      // draw_rectangle(x_inner, y_inner, 2.0, 2.0, color);
+
+      draw_line(x_inner, y_inner, x_outer, y_outer, state.line_gap, color)
+
+      // draw_rectangle(gap * i as f32, 0f32, gap, value.abs(), color);
+      //
+      // draw_rectangle(
+      //   gap * i as f32,
+      //   screen_height() / 2f32 - value / 2f32,
+      //   gap,
+      //   value.abs() * 2f32,
+      //   color,
+      // );
+      //
+      // draw_rectangle(gap * i as f32, screen_height() - value, gap, value.abs(), color);
+    }
+  }
+
+  /// Changes the device, keeping the error around so the UI can show it
+  fn try_change_device(&mut self, new_device: impl ToSerializableAudioDevice) {
+    self.settings.state.audio.error = match self.change_device(new_device) {
+      Ok(()) => None,
+      Err(err) => {
+        eprintln!("{}", err);
+        Some(err.to_string())
+      }
+    };
+  }
+
+  fn try_change_state(&mut self, state: PlaybackState) {
+    if let Err(err) = self.change_state(state) {
+      eprintln!("{}", err);
+      self.settings.state.audio.error = Some(err.to_string());
+    }
+  }
+
+  fn try_toggle_playing(&mut self) {
+    if let Err(err) = self.toggle_playing() {
+      eprintln!("{}", err);
+      self.settings.state.audio.error = Some(err.to_string());
+    }
+  }
+
+  /// Lists the devices going in `direction`, listing them opens every device on some hosts
+  /// so it's only done once until it's refreshed
+  #[cfg(not(target_os = "linux"))]
+  fn device_list(&mut self, ui: &mut egui::Ui, direction: DeviceDirection) {
+    if ui.button("Refresh").clicked() {
+      self.settings.state.audio.devices = None;
+    }
+
+    let audio = &self.audio;
+    let state = &mut self.settings.state.audio;
+    let devices = match state.devices.get_or_insert_with(|| audio.devices().map_err(|it| it.to_string())) {
+      Ok(devices) => devices,
+      Err(err) => {
+        ui.label(err.as_str());
+        return;
+      }
+    };
+
+    let selected = match direction {
+      DeviceDirection::Input => &state.input_device,
+      DeviceDirection::Output => &state.output_device,
+    };
+
+    let mut clicked = None;
+
+    for device in devices.iter().filter(|it| it.direction == direction) {
+      let is_selected = selected.as_ref() == Some(&device.name);
+
+      if ui.selectable_label(is_selected, device.name.as_str()).on_hover_text(describe_device(device)).clicked() {
+        clicked = Some(device.to_audio_device());
+      };
+    }
+
+    if let Some(device) = clicked {
+      self.try_change_device(device);
+    }
+  }
+
+  /// Records to `recording-<unix time>.wav` in the working directory
+  fn toggle_recording(&mut self) {
+    let result = if self.audio.is_recording() {
+      self.audio.stop_recording()
+    } else {
+      let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+      self.audio.start_recording(format!("recording-{}.wav", time))
+    };
+
+    if let Err(err) = result {
+      eprintln!("{}", err);
+      self.settings.state.audio.error = Some(err.to_string());
+    }
+  }
+
   fn on_track_change(&mut self) {
     if self.changed.load(Ordering::SeqCst) {
       self.set_textures(true);
@@ -290,8 +505,9 @@ impl Application for App {
       .iter()
       .position(|it| *it == self.settings.audio.mode)
       .unwrap_or_default();
+    self.settings.state.audio.stream = self.settings.audio.stream;
 
-    self.change_device(self.settings.audio.device.clone());
+    self.try_change_device(self.settings.audio.device.clone());
 
     let track = self.track.clone();
     let state = self.state.clone();
@@ -366,6 +582,147 @@ impl Application for App {
             self.change_mode(AudioMode::ALL[self.settings.state.audio.mode_index]);
           }
 
+          egui::ComboBox::from_label("Channels")
+            .selected_text(self.settings.audio.channel_mode.name())
+            .show_ui(ui, |ui| {
+              for mode in ChannelMode::ALL {
+                if ui.selectable_label(self.settings.audio.channel_mode == *mode, mode.name()).clicked() {
+                  self.change_channel_mode(*mode);
+                }
+              }
+            });
+
+          if let AudioMode::FFT(_) = self.settings.audio.mode {
+            let window = self.settings.audio.window;
+
+            egui::ComboBox::from_label("Window")
+              .selected_text(window.name())
+              .show_ui(ui, |ui| {
+                for function in WindowFunction::ALL {
+                  if ui.selectable_label(window.is_same_kind(function), function.name()).clicked() && !window.is_same_kind(function) {
+                    self.change_window(*function);
+                  }
+                }
+              });
+
+            if let WindowFunction::Kaiser { mut beta } = window {
+              if ui.add(egui::Slider::new(&mut beta, 0f32..=20f32).text("Kaiser Beta")).changed() {
+                self.change_window(WindowFunction::Kaiser { beta });
+              }
+            }
+
+            let scale = self.settings.audio.scale;
+
+            egui::ComboBox::from_label("Scale")
+              .selected_text(scale.name())
+              .show_ui(ui, |ui| {
+                for option in MagnitudeScale::ALL {
+                  if ui.selectable_label(scale.is_same_kind(option), option.name()).clicked() && !scale.is_same_kind(option) {
+                    self.change_scale(*option);
+                  }
+                }
+              });
+
+            if let MagnitudeScale::Decibel { mut floor, mut ceiling } = scale {
+              let changed = ui.add(egui::Slider::new(&mut floor, -160f32..=ceiling - 1f32).text("Floor (dB)")).changed()
+                | ui.add(egui::Slider::new(&mut ceiling, floor + 1f32..=20f32).text("Ceiling (dB)")).changed();
+
+              if changed {
+                self.change_scale(MagnitudeScale::Decibel { floor, ceiling });
+              }
+            }
+
+            let mut bands = self.settings.audio.bands;
+            let mut bands_changed = ui.checkbox(&mut bands.enabled, "Log Bands").changed();
+
+            if bands.enabled {
+              bands_changed |= ui.add(egui::Slider::new(&mut bands.bands_per_octave, 1..=24).text("Bands per Octave")).changed();
+              bands_changed |= ui.add(egui::Slider::new(&mut bands.min_hz, 10f32..=1000f32).logarithmic(true).text("Min Hz")).changed();
+              bands_changed |= ui.add(egui::Slider::new(&mut bands.max_hz, 1000f32..=24000f32).logarithmic(true).text("Max Hz")).changed();
+
+              egui::ComboBox::from_label("Aggregation")
+                .selected_text(bands.aggregation.name())
+                .show_ui(ui, |ui| {
+                  for aggregation in BandAggregation::ALL {
+                    bands_changed |= ui.selectable_value(&mut bands.aggregation, *aggregation, aggregation.name()).clicked();
+                  }
+                });
+            }
+
+            if bands_changed {
+              self.change_bands(bands);
+            }
+
+            let mut stft = self.settings.audio.stft;
+            let mut stft_changed = ui.checkbox(&mut stft.enabled, "Overlapping Frames").changed();
+
+            if stft.enabled {
+              stft_changed |= ui.add(egui::Slider::new(&mut stft.overlap, 0f32..=StftSettings::MAX_OVERLAP).text("Overlap")).changed();
+            }
+
+            if stft_changed {
+              self.change_stft(stft);
+            }
+          }
+
+          let mut agc = self.settings.audio.agc;
+          let mut agc_changed = ui.checkbox(&mut agc.enabled, "Automatic Gain").changed();
+
+          if agc.enabled {
+            agc_changed |= ui.add(egui::Slider::new(&mut agc.target, 0.01f32..=1f32).text("Target")).changed();
+            agc_changed |= ui.add(egui::Slider::new(&mut agc.attack, 0.001f32..=1f32).logarithmic(true).text("Attack")).changed();
+            agc_changed |= ui.add(egui::Slider::new(&mut agc.release, 0.01f32..=10f32).logarithmic(true).text("Release")).changed();
+            agc_changed |= ui.add(egui::Slider::new(&mut agc.max_gain, 1f32..=1000f32).logarithmic(true).text("Max Gain")).changed();
+          }
+
+          if agc_changed {
+            self.change_agc(agc);
+          }
+
+          let mut silence = self.settings.audio.silence;
+          let silence_changed = ui.add(egui::Slider::new(&mut silence.threshold, 0.0001f32..=0.1f32).logarithmic(true).text("Silence Threshold")).changed()
+            | ui.add(egui::Slider::new(&mut silence.hold, 0f32..=10f32).text("Silence Hold")).changed();
+
+          if silence_changed {
+            self.change_silence(silence);
+          }
+
+          if let Some(audio) = self.audio.data() {
+            ui.label(format!("{} Hz, {} Channels", audio.sample_rate, audio.channel_count));
+
+            if let Some(width) = audio.bin_width() {
+              ui.label(format!("{:.2} Hz per Bin", width));
+            }
+
+            ui.label(format!("Frame {}, {:.1} ms Latency", audio.frame, audio.age().as_secs_f64() * 1000.0));
+          }
+
+          ui.horizontal(|ui| {
+            let label = if self.audio.is_playing() { "Pause" } else { "Play" };
+
+            if ui.button(label).on_hover_text("Space").clicked() {
+              self.try_toggle_playing();
+            }
+
+            if ui.button("Stop").clicked() {
+              self.try_change_state(PlaybackState::Stopped);
+            }
+
+            ui.label(self.audio.state().name());
+          });
+
+          ui.horizontal(|ui| {
+            let label = if self.audio.is_recording() { "Stop Recording" } else { "Record" };
+
+            if ui.button(label).on_hover_text("R").clicked() {
+              self.toggle_recording();
+            }
+
+            if let Some(recording) = self.audio.recording() {
+              ui.colored_label(egui::Color32::RED, recording.path().display().to_string());
+            }
+          });
+
           let response = egui::ComboBox::from_label("Device Type")
             .selected_text(format!("{:?}", self.settings.state.audio.device_type))
             .show_ui(ui, |ui| {
@@ -375,34 +732,25 @@ impl Application for App {
                 ui.selectable_value(&mut self.settings.state.audio.device_type, AudioDeviceType::Loopback, "Loopback").clicked(),
                 ui.selectable_value(&mut self.settings.state.audio.device_type, AudioDeviceType::Input, "Input").clicked(),
                 ui.selectable_value(&mut self.settings.state.audio.device_type, AudioDeviceType::Output, "Output").clicked(),
+                ui.selectable_value(&mut self.settings.state.audio.device_type, AudioDeviceType::File, "File").clicked(),
+                ui.selectable_value(&mut self.settings.state.audio.device_type, AudioDeviceType::Generator, "Generator").clicked(),
+                ui.selectable_value(&mut self.settings.state.audio.device_type, AudioDeviceType::Pipe, "Pipe").clicked(),
               ]
             });
 
           let changed = response.inner.unwrap_or_default().contains(&true);
 
           match self.settings.state.audio.device_type {
-            AudioDeviceType::None if changed => self.change_device(AudioDevice::NONE),
-            AudioDeviceType::Default if changed => self.change_device(AudioDevice::DEFAULT),
-            AudioDeviceType::Loopback if changed => self.change_device(AudioDevice::LOOPBACK),
+            AudioDeviceType::None if changed => self.try_change_device(AudioDevice::NONE),
+            AudioDeviceType::Default if changed => self.try_change_device(AudioDevice::DEFAULT),
+            AudioDeviceType::Loopback if changed => self.try_change_device(AudioDevice::LOOPBACK),
             AudioDeviceType::Input => {
               #[cfg(target_os = "linux")]
               ui.label(AUDIO_DEVICE_SWITCH_NOT_SUPPORT);
               #[cfg(not(target_os = "linux"))]
               egui::ComboBox::from_label("Input Device")
                 .selected_text(format!("{:.20}", self.settings.state.audio.input_device.clone().unwrap_or_default()))
-                .show_ui(ui, |ui| {
-                  let devices = self.audio.host().input_devices().unwrap();
-                  for device in devices {
-                    let name = match device.name() {
-                      Ok(name) => name,
-                      Err(_) => continue,
-                    };
-
-                    if ui.selectable_label(false, name.clone()).clicked() {
-                      self.change_device(AudioDevice::Input(name));
-                    };
-                  }
-                });
+                .show_ui(ui, |ui| self.device_list(ui, DeviceDirection::Input));
             }
             AudioDeviceType::Output => {
               #[cfg(target_os = "linux")]
@@ -410,22 +758,102 @@ impl Application for App {
               #[cfg(not(target_os = "linux"))]
               egui::ComboBox::from_label("Output Device")
                 .selected_text(format!("{:.20}", self.settings.state.audio.output_device.clone().unwrap_or_default()))
+                .show_ui(ui, |ui| self.device_list(ui, DeviceDirection::Output));
+            }
+            AudioDeviceType::File => {
+              ui.text_edit_singleline(&mut self.settings.state.audio.file_path);
+
+              if ui.button("Open").clicked() {
+                let path = self.settings.state.audio.file_path.clone();
+                self.try_change_device(AudioDevice::file(FileSettings::new(path)));
+              }
+            }
+            AudioDeviceType::Generator => {
+              let generator = &mut self.settings.state.audio.generator;
+
+              egui::ComboBox::from_label("Signal")
+                .selected_text(generator.signal.name())
+                .show_ui(ui, |ui| {
+                  for signal in Signal::DEFAULTS {
+                    ui.selectable_value(&mut generator.signal, *signal, signal.name());
+                  }
+                });
+
+              match &mut generator.signal {
+                Signal::Sine { frequency } | Signal::Square { frequency } | Signal::Impulse { frequency } => {
+                  ui.add(egui::Slider::new(frequency, 1f32..=20000f32).logarithmic(true).text("Frequency"));
+                }
+                Signal::Sweep { duration, .. } => {
+                  ui.add(egui::Slider::new(duration, 1f32..=60f32).text("Duration"));
+                }
+                _ => {}
+              }
+
+              ui.add(egui::Slider::new(&mut generator.amplitude, 0f32..=1f32).text("Amplitude"));
+
+              if ui.button("Start").clicked() {
+                let generator = generator.clone();
+                self.try_change_device(AudioDevice::generator(generator));
+              }
+            }
+            AudioDeviceType::Pipe => {
+              let audio = &mut self.settings.state.audio;
+
+              ui.label("Path (empty for stdin)");
+              ui.text_edit_singleline(&mut audio.pipe_path);
+
+              egui::ComboBox::from_label("Format")
+                .selected_text(audio.pipe.format.name())
                 .show_ui(ui, |ui| {
-                  let devices = self.audio.host().output_devices().unwrap();
-                  for device in devices {
-                    let name = match device.name() {
-                      Ok(name) => name,
-                      Err(_) => continue,
-                    };
-
-                    if ui.selectable_label(false, name.clone()).clicked() {
-                      self.change_device(AudioDevice::Output(name));
-                    };
+                  for format in PcmFormat::ALL {
+                    ui.selectable_value(&mut audio.pipe.format, *format, format.name());
                   }
                 });
+
+              ui.add(egui::Slider::new(&mut audio.pipe.sample_rate, 8000..=192000).text("Sample Rate"));
+              ui.add(egui::Slider::new(&mut audio.pipe.channels, 1..=8).text("Channels"));
+
+              if ui.button("Open").clicked() {
+                let mut pipe = audio.pipe.clone();
+                pipe.path = Some(audio.pipe_path.clone()).filter(|it| !it.is_empty()).map(Into::into);
+
+                self.try_change_device(AudioDevice::pipe(pipe));
+              }
             }
             _ => {}
           }
+
+          if let Some(error) = &self.settings.state.audio.error {
+            ui.colored_label(egui::Color32::RED, error);
+          }
+
+          match self.audio.status() {
+            status @ SourceStatus::Recovering { .. } => {
+              ui.colored_label(egui::Color32::YELLOW, status.to_string());
+            }
+            status => {
+              ui.label(status.to_string());
+            }
+          }
+
+          ui.checkbox(&mut self.settings.audio.watchdog.enabled, "Reconnect");
+        });
+
+        egui::CollapsingHeader::new("Stream").default_open(false).show(ui, |ui| {
+          let stream = &mut self.settings.state.audio.stream;
+
+          optional_slider(ui, &mut stream.sample_rate, 8000..=192000, 48000, "Sample Rate");
+          optional_slider(ui, &mut stream.buffer_size, 32..=8192, 512, "Buffer Size");
+          optional_slider(ui, &mut stream.channels, 1..=8, 2, "Channels");
+
+          if ui.button("Apply").clicked() {
+            let stream = *stream;
+
+            if let Err(err) = self.change_stream_settings(stream) {
+              eprintln!("{}", err);
+              self.settings.state.audio.error = Some(err.to_string());
+            }
+          }
         });
 
         egui::CollapsingHeader::new("Color").default_open(true).show(ui, |ui| {
@@ -470,13 +898,36 @@ impl Application for App {
   }
   //endregion
 
-  fn before_draw(&mut self) {
+  fn before_draw(&mut self, ctx: &CtxRef) {
     self.on_track_change();
     self.set_textures(false);
+    self.watch();
 
     if is_key_pressed(KeyCode::H) {
       self.settings.state.show_ui = !self.settings.state.show_ui;
     }
+
+    for event in self.audio.events() {
+      self.settings.state.audio.silent = event == AudioEvent::SilenceStarted;
+    }
+
+    let audio = &mut self.settings.state.audio;
+    let step = get_frame_time() / FADE_TIME;
+
+    audio.fade = if audio.silent { (audio.fade - step).max(0f32) } else { (audio.fade + step).min(1f32) };
+
+    // Typing in a text field shouldn't also pause or start recording
+    if ctx.wants_keyboard_input() {
+      return;
+    }
+
+    if is_key_pressed(KeyCode::R) {
+      self.toggle_recording();
+    }
+
+    if is_key_pressed(KeyCode::Space) {
+      self.try_toggle_playing();
+    }
   }
 
   fn draw(&self, ctx: &CtxRef) {
@@ -486,15 +937,16 @@ impl Application for App {
     clear_background(self.settings.state.bg_color.as_color());
 
     let color = if matches!(self.get_track_state(), TrackState::Playing) { 128 } else { 32 };
+    let alpha = (self.settings.state.audio.fade * 255f32) as u8;
 
     let (x, y) = App::center(&self.bg_texture);
-    draw_texture(self.bg_texture, x, y, Color::gray_scale(color));
+    draw_texture(self.bg_texture, x, y, Color::gray_scale_alpha(color, alpha));
 
     let (x, y) = App::bottom_left(&self.cover_texture);
-    draw_texture(self.cover_texture, 70f32 + x, y - 150f32, Color::gray_scale(color + 96));
+    draw_texture(self.cover_texture, 70f32 + x, y - 150f32, Color::gray_scale_alpha(color + 96, alpha));
 
 
-    self.fonts.draw_text(&track.title, 70f32 + x, y + 120f32, 48, Color::gray_scale(235));
+    self.fonts.draw_text(&track.title, 70f32 + x, y + 120f32, 48, Color::gray_scale_alpha(235, alpha));
     // egui_draw_text(ctx, &self.track.title, 70f32 + x, y + 120f32, 48, Color::gray_scale(240));
     // draw_text_ex(&self.track.title, 70f32 + x, y + 175f32, TextParams {
     //   font_size: 48,
@@ -505,42 +957,15 @@ impl Application for App {
     // });
 
     if let Some(audio) = self.audio.data() {
-      let len = audio.len();
-      let center_w = state.offset_x + screen_width() / 2f32;
-      let center_h = state.offset_y + screen_height() / 2f32;
-
-      for i in 0..len {
-        let value = audio[i] * 300f32 * state.size;
-        let mut color = self.settings.state.fg_color.as_color();
-        let gap = screen_width() / len as f32;
-
-        color.r = clamp(color.r * audio[i] * 5f32, 0.2, 1.0);
-        color.g = clamp(color.g * audio[i] * 5f32, 0.2, 1.0);
-        color.b = clamp(color.b * audio[i] * 5f32, 0.2, 1.0);
-
-        let theta = (TAU / len as f32) * i as f32;
-        let radius = state.radius * audio.sum / 200f32;
-
-        let x_inner = center_w + (radius - value) * theta.sin();
-        let y_inner = center_h - (radius - value) * theta.cos();
-        let x_outer = center_w + (radius + value) * theta.sin();
-        let y_outer = center_h - (radius + value) * theta.cos();
-
-        // draw_rectangle(x_inner, y_inner, 2.0, 2.0, color);
-
-        draw_line(x_inner, y_inner, x_outer, y_outer, state.line_gap, color)
-
-        // draw_rectangle(gap * i as f32, 0f32, gap, value.abs(), color);
-        //
-        // draw_rectangle(
-        //   gap * i as f32,
-        //   screen_height() / 2f32 - value / 2f32,
-        //   gap,
-        //   value.abs() * 2f32,
-        //   color,
-        // );
-        //
-        // draw_rectangle(gap * i as f32, screen_height() - value, gap, value.abs(), color);
+      if audio.channels.is_empty() {
+        self.draw_radial(App::values(&audio), audio.sum, 0f32, TAU);
+      } else {
+        // Every channel gets its own slice of the circle
+        let sweep = TAU / audio.channels.len() as f32;
+
+        for (i, channel) in audio.channels.iter().enumerate() {
+          self.draw_radial(App::values(channel), audio.sum, sweep * i as f32, sweep);
+        }
       }
     }
   }
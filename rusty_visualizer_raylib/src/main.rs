@@ -11,6 +11,7 @@ use rusty_visualizer_core::audio::{Audio, AudioMode};
 use rusty_visualizer_core::fft::FFTSize;
 use rusty_visualizer_core::settings::{AudioSettings, SettingsManager};
 use rusty_visualizer_core::util::AnyErrorResult;
+use rusty_visualizer_core::watchdog::SourceStatus;
 
 use crate::application::{Application, run_application};
 
@@ -102,9 +103,16 @@ impl Application for State {
         d.draw_line_ex(Vector2::new(x_inner, y_inner), Vector2::new(x_outer, y_outer), 1.0, color);
       }
     }
+
+    if let status @ SourceStatus::Recovering { .. } = audio.status() {
+      let y = d.get_screen_height() - 25;
+      d.draw_text(&status.to_string(), 5, y, 20, Color::YELLOW);
+    }
   }
 
   fn gui<G: RaylibDrawGui>(&mut self, d: &mut G) {
+    self.audio.watch(&self.settings.audio);
+
     self.scale = d.gui_slider(
       rrect(5, 5, 200, 30),
       None, rayui::rayui_str!("Scale"), self.scale,